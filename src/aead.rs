@@ -0,0 +1,858 @@
+use alloc::vec::Vec;
+
+use ring::aead::{self as ring_aead, Aad, LessSafeKey, Nonce, UnboundKey};
+
+use crate::error::Error;
+use crate::key::Key;
+use crate::nonce::{NonceStrategy, RandomNonce};
+
+/// Length in bytes of the nonce used by every supported algorithm.
+pub const NONCE_LEN: usize = 12;
+
+/// Length in bytes of the authentication tag appended to every ciphertext.
+pub const TAG_LEN: usize = 16;
+
+/// An authentication tag kept apart from its ciphertext, for protocols that
+/// carry the tag in a separate field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tag([u8; TAG_LEN]);
+
+impl Tag {
+    /// Returns the raw tag.
+    pub fn as_bytes(&self) -> &[u8; TAG_LEN] {
+        &self.0
+    }
+}
+
+impl From<[u8; TAG_LEN]> for Tag {
+    fn from(bytes: [u8; TAG_LEN]) -> Tag {
+        Tag(bytes)
+    }
+}
+
+/// Fails with `Error::TruncatedInput` unless the slice is [`TAG_LEN`] bytes.
+impl TryFrom<&[u8]> for Tag {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Tag, Error> {
+        Ok(Tag(bytes.try_into().map_err(|_| Error::TruncatedInput)?))
+    }
+}
+
+impl AsRef<[u8]> for Tag {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The AEAD algorithms supported by [`Cipher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// AES-128 in Galois/Counter Mode.
+    Aes128Gcm,
+    /// AES-192 in Galois/Counter Mode. Requires the `aes192` feature.
+    Aes192Gcm,
+    /// AES-256 in Galois/Counter Mode.
+    Aes256Gcm,
+    /// AES-256-GCM-SIV (RFC 8452), which tolerates accidental nonce reuse:
+    /// repeating a nonce only reveals whether two messages were identical.
+    /// Requires the `gcm-siv` feature.
+    Aes256GcmSiv,
+    /// ChaCha20-Poly1305 (RFC 8439), which is faster than AES in software on
+    /// CPUs without AES instructions.
+    ChaCha20Poly1305,
+}
+
+impl Algorithm {
+    /// Every algorithm variant, in declaration order.
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::Aes128Gcm,
+        Algorithm::Aes192Gcm,
+        Algorithm::Aes256Gcm,
+        Algorithm::Aes256GcmSiv,
+        Algorithm::ChaCha20Poly1305,
+    ];
+
+    /// Returns the conventional name of the algorithm, e.g. `"AES-256-GCM"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Aes128Gcm => "AES-128-GCM",
+            Algorithm::Aes192Gcm => "AES-192-GCM",
+            Algorithm::Aes256Gcm => "AES-256-GCM",
+            Algorithm::Aes256GcmSiv => "AES-256-GCM-SIV",
+            Algorithm::ChaCha20Poly1305 => "ChaCha20-Poly1305",
+        }
+    }
+
+    /// Returns the identifier of the algorithm in the envelope header.
+    pub fn id(self) -> u8 {
+        match self {
+            Algorithm::Aes128Gcm => 1,
+            Algorithm::Aes192Gcm => 2,
+            Algorithm::Aes256Gcm => 3,
+            Algorithm::Aes256GcmSiv => 4,
+            Algorithm::ChaCha20Poly1305 => 5,
+        }
+    }
+
+    /// Looks up an algorithm by its envelope header identifier.
+    pub fn from_id(id: u8) -> Option<Algorithm> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.id() == id)
+    }
+
+    /// Returns the key length in bytes required by the algorithm.
+    pub fn key_len(self) -> usize {
+        match self {
+            Algorithm::Aes128Gcm => 16,
+            Algorithm::Aes192Gcm => 24,
+            Algorithm::Aes256Gcm | Algorithm::Aes256GcmSiv | Algorithm::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    pub(crate) fn check_key_len(self, actual: usize) -> Result<(), Error> {
+        if actual == self.key_len() {
+            Ok(())
+        } else {
+            Err(Error::InvalidKeyLength {
+                expected: self.key_len(),
+                actual,
+            })
+        }
+    }
+}
+
+/// A key bound to an [`Algorithm`], ready to encrypt and decrypt.
+pub struct Cipher {
+    algorithm: Algorithm,
+    backend: Backend,
+}
+
+// Kept unboxed so that building a Cipher never allocates.
+#[allow(clippy::large_enum_variant)]
+enum Backend {
+    Ring(LessSafeKey),
+    #[cfg(feature = "aes192")]
+    Aes192(aes_gcm::AesGcm<aes_gcm::aes::Aes192, aes_gcm::aead::consts::U12>),
+    #[cfg(feature = "gcm-siv")]
+    GcmSiv(aes_gcm_siv::Aes256GcmSiv),
+}
+
+impl Cipher {
+    /// Creates a cipher for the given algorithm and key.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `key` - The key; its length must match `algorithm.key_len()`.
+    ///
+    /// # Returns
+    ///
+    /// The cipher, `Error::InvalidKeyLength` if the key does not fit the
+    /// algorithm, or `Error::UnsupportedAlgorithm` if the algorithm's backend
+    /// was not compiled in.
+    pub fn new(algorithm: Algorithm, key: &Key) -> Result<Cipher, Error> {
+        algorithm.check_key_len(key.as_bytes().len())?;
+
+        let backend =
+            match algorithm {
+                Algorithm::Aes128Gcm => ring_backend(&ring_aead::AES_128_GCM, key)?,
+                Algorithm::Aes256Gcm => ring_backend(&ring_aead::AES_256_GCM, key)?,
+                Algorithm::ChaCha20Poly1305 => ring_backend(&ring_aead::CHACHA20_POLY1305, key)?,
+                #[cfg(feature = "aes192")]
+                Algorithm::Aes192Gcm => {
+                    use aes_gcm::KeyInit;
+                    let cipher = aes_gcm::AesGcm::new_from_slice(key.as_bytes()).map_err(|_| {
+                        Error::InvalidKeyLength {
+                            expected: algorithm.key_len(),
+                            actual: key.as_bytes().len(),
+                        }
+                    })?;
+                    Backend::Aes192(cipher)
+                }
+                #[cfg(not(feature = "aes192"))]
+                Algorithm::Aes192Gcm => return Err(Error::UnsupportedAlgorithm),
+                #[cfg(feature = "gcm-siv")]
+                Algorithm::Aes256GcmSiv => {
+                    use aes_gcm_siv::KeyInit;
+                    let cipher = aes_gcm_siv::Aes256GcmSiv::new_from_slice(key.as_bytes())
+                        .map_err(|_| Error::InvalidKeyLength {
+                            expected: algorithm.key_len(),
+                            actual: key.as_bytes().len(),
+                        })?;
+                    Backend::GcmSiv(cipher)
+                }
+                #[cfg(not(feature = "gcm-siv"))]
+                Algorithm::Aes256GcmSiv => return Err(Error::UnsupportedAlgorithm),
+            };
+
+        Ok(Cipher { algorithm, backend })
+    }
+
+    /// Returns the algorithm this cipher was created for.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Encrypts the given plaintext under a fresh random nonce.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the encrypted ciphertext (with the tag appended) and
+    /// the nonce used for encryption.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        self.encrypt_with_aad(plaintext, &[])
+    }
+
+    /// Encrypts the given plaintext under a fresh random nonce, authenticating
+    /// the associated data along with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The data to encrypt.
+    /// * `aad` - Additional data that is authenticated but not encrypted. The
+    ///   same bytes must be passed to [`Cipher::decrypt_with_aad`].
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the encrypted ciphertext (with the tag appended) and
+    /// the nonce used for encryption.
+    pub fn encrypt_with_aad(
+        &self,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let mut in_out = Vec::with_capacity(plaintext.len() + TAG_LEN);
+        in_out.extend_from_slice(plaintext);
+        let nonce = self.seal_in_place(&mut in_out, aad)?;
+        Ok((in_out, nonce.to_vec()))
+    }
+
+    /// Encrypts a plaintext given as several fragments, e.g. a header, body
+    /// and trailer, without concatenating them first.
+    ///
+    /// The fragments are copied straight into the output buffer. The
+    /// associated data fragments are joined, since the backends need them in
+    /// one piece; keep the large parts of a message in the plaintext.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The fragments of the data to encrypt, in order.
+    /// * `aad` - The fragments of the associated data, in order. Decryption
+    ///   takes their concatenation.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the ciphertext of the concatenated fragments (with
+    /// the tag appended) and the nonce used for encryption.
+    pub fn encrypt_vectored(
+        &self,
+        plaintext: &[&[u8]],
+        aad: &[&[u8]],
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let len = plaintext.iter().map(|part| part.len()).sum::<usize>();
+        let mut in_out = Vec::with_capacity(len + TAG_LEN);
+        for part in plaintext {
+            in_out.extend_from_slice(part);
+        }
+        let nonce = match aad {
+            [] => self.seal_in_place(&mut in_out, &[])?,
+            [aad] => self.seal_in_place(&mut in_out, aad)?,
+            _ => self.seal_in_place(&mut in_out, &aad.concat())?,
+        };
+        Ok((in_out, nonce.to_vec()))
+    }
+
+    /// Encrypts a buffer in place under a fresh random nonce.
+    ///
+    /// The tag is appended to `in_out`, so no allocation takes place when the
+    /// buffer has [`TAG_LEN`] bytes of spare capacity.
+    ///
+    /// # Arguments
+    ///
+    /// * `in_out` - The plaintext on input, the ciphertext with the tag
+    ///   appended on output.
+    /// * `aad` - Additional data that is authenticated but not encrypted.
+    ///
+    /// # Returns
+    ///
+    /// The nonce used for encryption.
+    pub fn seal_in_place(
+        &self,
+        in_out: &mut Vec<u8>,
+        aad: &[u8],
+    ) -> Result<[u8; NONCE_LEN], Error> {
+        let nonce = RandomNonce.next_nonce()?;
+        self.seal_in_place_with_nonce(nonce, in_out, aad)?;
+        Ok(nonce)
+    }
+
+    /// Encrypts the given plaintext under the next nonce of a caller-chosen
+    /// [`NonceStrategy`].
+    ///
+    /// # Arguments
+    ///
+    /// * `nonces` - The nonce policy, e.g. a [`NonceSequence`](crate::nonce::NonceSequence).
+    /// * `plaintext` - The data to encrypt.
+    /// * `aad` - Additional data that is authenticated but not encrypted.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the encrypted ciphertext (with the tag appended) and
+    /// the nonce used for encryption, or `Error::NonceExhausted` if the
+    /// strategy has no unused nonce left.
+    pub fn encrypt_with_strategy<S: NonceStrategy + ?Sized>(
+        &self,
+        nonces: &mut S,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, [u8; NONCE_LEN]), Error> {
+        let nonce = nonces.next_nonce()?;
+        Ok((self.encrypt_with_nonce(nonce, plaintext, aad)?, nonce))
+    }
+
+    /// Encrypts the given plaintext and returns the tag separately instead of
+    /// appending it.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonces` - The nonce policy, e.g. [`RandomNonce`] or a
+    ///   [`FixedNonce`](crate::nonce::FixedNonce) mandated by the protocol.
+    /// * `plaintext` - The data to encrypt.
+    /// * `aad` - Additional data that is authenticated but not encrypted.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the ciphertext, which is as long as the plaintext,
+    /// the tag and the nonce used for encryption.
+    pub fn seal_detached<S: NonceStrategy + ?Sized>(
+        &self,
+        nonces: &mut S,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Tag, [u8; NONCE_LEN]), Error> {
+        let nonce = nonces.next_nonce()?;
+        let mut ciphertext = plaintext.to_vec();
+        let tag = self.encrypt_in_place_detached(&nonce, aad, &mut ciphertext)?;
+        Ok((ciphertext, Tag(tag), nonce))
+    }
+
+    /// Encrypts under a caller-chosen nonce, which must never repeat for this key.
+    pub(crate) fn encrypt_with_nonce(
+        &self,
+        nonce: [u8; NONCE_LEN],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut in_out = Vec::with_capacity(plaintext.len() + TAG_LEN);
+        in_out.extend_from_slice(plaintext);
+        self.seal_in_place_with_nonce(nonce, &mut in_out, aad)?;
+        Ok(in_out)
+    }
+
+    fn seal_in_place_with_nonce(
+        &self,
+        nonce: [u8; NONCE_LEN],
+        in_out: &mut Vec<u8>,
+        aad: &[u8],
+    ) -> Result<(), Error> {
+        let tag = self.encrypt_in_place_detached(&nonce, aad, in_out)?;
+        in_out.extend_from_slice(&tag);
+        Ok(())
+    }
+
+    /// Encrypts `in_out` in place and returns the tag separately, without allocating.
+    pub(crate) fn encrypt_in_place_detached(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        in_out: &mut [u8],
+    ) -> Result<[u8; TAG_LEN], Error> {
+        let mut out = [0u8; TAG_LEN];
+        match &self.backend {
+            Backend::Ring(key) => {
+                let nonce = Nonce::assume_unique_for_key(*nonce);
+                let tag = key
+                    .seal_in_place_separate_tag(nonce, Aad::from(aad), in_out)
+                    .map_err(|_| Error::EncryptionFailed)?;
+                out.copy_from_slice(tag.as_ref());
+            }
+            #[cfg(feature = "aes192")]
+            Backend::Aes192(key) => {
+                use aes_gcm::aead::AeadInPlace;
+                let tag = key
+                    .encrypt_in_place_detached(nonce.into(), aad, in_out)
+                    .map_err(|_| Error::EncryptionFailed)?;
+                out.copy_from_slice(&tag);
+            }
+            #[cfg(feature = "gcm-siv")]
+            Backend::GcmSiv(key) => {
+                use aes_gcm_siv::aead::AeadInPlace;
+                let tag = key
+                    .encrypt_in_place_detached(nonce.into(), aad, in_out)
+                    .map_err(|_| Error::EncryptionFailed)?;
+                out.copy_from_slice(&tag);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Decrypts the given ciphertext.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonce` - The nonce used for encryption.
+    /// * `ciphertext` - The data to decrypt, tag included.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext.
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        self.decrypt_with_aad(nonce, ciphertext, &[])
+    }
+
+    /// Decrypts the given ciphertext and checks the associated data.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonce` - The nonce used for encryption.
+    /// * `ciphertext` - The data to decrypt, tag included.
+    /// * `aad` - The associated data passed at encryption time.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext. Fails with `Error::DecryptionFailed` if the key
+    /// is wrong or the ciphertext or associated data was modified, and with
+    /// `Error::InvalidNonce` if the nonce is not [`NONCE_LEN`] bytes long.
+    pub fn decrypt_with_aad(
+        &self,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let mut in_out = ciphertext.to_vec();
+        let plaintext_len = self.open_in_place(nonce, &mut in_out, aad)?.len();
+
+        // Drop the authentication tag from the end of the buffer
+        in_out.truncate(plaintext_len);
+        Ok(in_out)
+    }
+
+    /// Decrypts a ciphertext whose tag was transmitted separately.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonce` - The nonce used for encryption.
+    /// * `ciphertext` - The data to decrypt, without the tag.
+    /// * `tag` - The tag returned by [`Cipher::seal_detached`].
+    /// * `aad` - The associated data passed at encryption time.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext. Fails like [`Cipher::decrypt_with_aad`].
+    pub fn open_detached(
+        &self,
+        nonce: &[u8],
+        ciphertext: &[u8],
+        tag: &Tag,
+        aad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let nonce: &[u8; NONCE_LEN] = nonce.try_into().map_err(|_| Error::InvalidNonce)?;
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt_in_place_detached(nonce, aad, &tag.0, &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    /// Decrypts a buffer in place, without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonce` - The nonce used for encryption.
+    /// * `in_out` - The ciphertext with the tag appended; overwritten with the
+    ///   plaintext.
+    /// * `aad` - The associated data passed at encryption time.
+    ///
+    /// # Returns
+    ///
+    /// The plaintext, which is the start of `in_out` without the tag. Fails
+    /// like [`Cipher::decrypt_with_aad`].
+    pub fn open_in_place<'a>(
+        &self,
+        nonce: &[u8],
+        in_out: &'a mut [u8],
+        aad: &[u8],
+    ) -> Result<&'a mut [u8], Error> {
+        let nonce: &[u8; NONCE_LEN] = nonce.try_into().map_err(|_| Error::InvalidNonce)?;
+        let plaintext_len = in_out
+            .len()
+            .checked_sub(TAG_LEN)
+            .ok_or(Error::TruncatedInput)?;
+
+        let (ciphertext, tag) = in_out.split_at_mut(plaintext_len);
+        let tag: &[u8; TAG_LEN] = (&*tag).try_into().expect("tag is TAG_LEN bytes");
+        self.decrypt_in_place_detached(nonce, aad, tag, ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    /// Decrypts `in_out` in place against a detached tag, without allocating.
+    pub(crate) fn decrypt_in_place_detached(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        tag: &[u8; TAG_LEN],
+        in_out: &mut [u8],
+    ) -> Result<(), Error> {
+        match &self.backend {
+            Backend::Ring(key) => {
+                let nonce = Nonce::assume_unique_for_key(*nonce);
+                let tag = ring_aead::Tag::from(*tag);
+                key.open_in_place_separate_tag(nonce, Aad::from(aad), tag, in_out, 0..)
+                    .map_err(|_| Error::DecryptionFailed)?;
+            }
+            #[cfg(feature = "aes192")]
+            Backend::Aes192(key) => {
+                use aes_gcm::aead::{generic_array::GenericArray, AeadInPlace};
+                key.decrypt_in_place_detached(
+                    nonce.into(),
+                    aad,
+                    in_out,
+                    GenericArray::from_slice(tag),
+                )
+                .map_err(|_| Error::DecryptionFailed)?;
+            }
+            #[cfg(feature = "gcm-siv")]
+            Backend::GcmSiv(key) => {
+                use aes_gcm_siv::aead::{generic_array::GenericArray, AeadInPlace};
+                key.decrypt_in_place_detached(
+                    nonce.into(),
+                    aad,
+                    in_out,
+                    GenericArray::from_slice(tag),
+                )
+                .map_err(|_| Error::DecryptionFailed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn ring_backend(algorithm: &'static ring_aead::Algorithm, key: &Key) -> Result<Backend, Error> {
+    let key = UnboundKey::new(algorithm, key.as_bytes()).map_err(|_| Error::InvalidKeyLength {
+        expected: algorithm.key_len(),
+        actual: key.as_bytes().len(),
+    })?;
+    Ok(Backend::Ring(LessSafeKey::new(key)))
+}
+
+/// Encrypts the given plaintext using AES-256-GCM encryption.
+///
+/// # Arguments
+///
+/// * `key` - The AES-256 key.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// A tuple containing the encrypted ciphertext and the nonce used for encryption.
+pub fn encrypt_aes_256_gcm(key: &Key, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    encrypt_aes_256_gcm_with_aad(key, plaintext, &[])
+}
+
+/// Encrypts the given plaintext using AES-256-GCM encryption, authenticating
+/// the associated data along with it.
+///
+/// # Arguments
+///
+/// * `key` - The AES-256 key.
+/// * `plaintext` - The data to encrypt.
+/// * `aad` - Additional data that is authenticated but not encrypted, such as a
+///   record header or user ID.
+///
+/// # Returns
+///
+/// A tuple containing the encrypted ciphertext and the nonce used for encryption.
+pub fn encrypt_aes_256_gcm_with_aad(
+    key: &Key,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    Cipher::new(Algorithm::Aes256Gcm, key)?.encrypt_with_aad(plaintext, aad)
+}
+
+/// Decrypts the given ciphertext using AES-256-GCM encryption.
+///
+/// # Arguments
+///
+/// * `key` - The AES-256 key.
+/// * `nonce` - The nonce used for encryption.
+/// * `ciphertext` - The data to decrypt.
+///
+/// # Returns
+///
+/// The decrypted plaintext.
+pub fn decrypt_aes_256_gcm(key: &Key, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    decrypt_aes_256_gcm_with_aad(key, nonce, ciphertext, &[])
+}
+
+/// Decrypts the given ciphertext using AES-256-GCM encryption and checks the
+/// associated data.
+///
+/// # Arguments
+///
+/// * `key` - The AES-256 key.
+/// * `nonce` - The nonce used for encryption.
+/// * `ciphertext` - The data to decrypt.
+/// * `aad` - The associated data passed at encryption time.
+///
+/// # Returns
+///
+/// The decrypted plaintext.
+pub fn decrypt_aes_256_gcm_with_aad(
+    key: &Key,
+    nonce: &[u8],
+    ciphertext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Cipher::new(Algorithm::Aes256Gcm, key)?.decrypt_with_aad(nonce, ciphertext, aad)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aes_256_gcm_encryption_decryption() {
+        let key = Key::new(b"an example very very secret key.").unwrap(); // 32 bytes
+        let plaintext = b"hello world";
+
+        let (ciphertext, nonce) = encrypt_aes_256_gcm(&key, plaintext).expect("encryption failed");
+        let decrypted_plaintext =
+            decrypt_aes_256_gcm(&key, &nonce, &ciphertext).expect("decryption failed");
+
+        assert_eq!(plaintext.to_vec(), decrypted_plaintext);
+    }
+
+    #[test]
+    fn test_nonces_are_not_reused() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+
+        let (first, first_nonce) = encrypt_aes_256_gcm(&key, b"hello world").unwrap();
+        let (second, second_nonce) = encrypt_aes_256_gcm(&key, b"hello world").unwrap();
+
+        assert_ne!(first_nonce, second_nonce);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_invalid_key_length() {
+        assert_eq!(
+            Key::new(b"too short").err(),
+            Some(Error::UnsupportedKeyLength { actual: 9 })
+        );
+
+        let key = Key::new(b"sixteen byte key").unwrap();
+        assert_eq!(
+            Cipher::new(Algorithm::Aes256Gcm, &key).err(),
+            Some(Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_aes_128_gcm_encryption_decryption() {
+        let key = Key::for_algorithm(Algorithm::Aes128Gcm, b"sixteen byte key").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes128Gcm, &key).unwrap();
+
+        let (ciphertext, nonce) = cipher.encrypt(b"hello world").expect("encryption failed");
+        assert_eq!(ciphertext.len(), b"hello world".len() + TAG_LEN);
+        assert_eq!(cipher.decrypt(&nonce, &ciphertext).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn test_aad_is_authenticated() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+
+        let (ciphertext, nonce) =
+            encrypt_aes_256_gcm_with_aad(&key, b"hello world", b"user:42").unwrap();
+
+        assert_eq!(
+            decrypt_aes_256_gcm_with_aad(&key, &nonce, &ciphertext, b"user:42").unwrap(),
+            b"hello world"
+        );
+        assert_eq!(
+            decrypt_aes_256_gcm_with_aad(&key, &nonce, &ciphertext, b"user:43"),
+            Err(Error::DecryptionFailed)
+        );
+        assert!(decrypt_aes_256_gcm(&key, &nonce, &ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_vectored() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+
+        let (ciphertext, nonce) = cipher
+            .encrypt_vectored(&[b"hello", b" ", b"world"], &[b"user:", b"42"])
+            .unwrap();
+        assert_eq!(
+            cipher
+                .decrypt_with_aad(&nonce, &ciphertext, b"user:42")
+                .unwrap(),
+            b"hello world"
+        );
+        assert_eq!(
+            cipher.decrypt_with_aad(&nonce, &ciphertext, b"user:"),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_encrypt_with_nonce_sequence() {
+        use crate::nonce::NonceSequence;
+
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let mut nonces = NonceSequence::new([0; 4]);
+
+        let (first, first_nonce) = cipher
+            .encrypt_with_strategy(&mut nonces, b"hello world", b"")
+            .unwrap();
+        let (_, second_nonce) = cipher
+            .encrypt_with_strategy(&mut nonces, b"hello world", b"")
+            .unwrap();
+
+        assert_eq!(first_nonce, [0; NONCE_LEN]);
+        assert_eq!(second_nonce[NONCE_LEN - 1], 1);
+        assert_eq!(
+            cipher.decrypt(&first_nonce, &first).unwrap(),
+            b"hello world"
+        );
+    }
+
+    #[test]
+    fn test_detached_tag() {
+        use crate::nonce::FixedNonce;
+
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+
+        let (ciphertext, tag, nonce) = cipher
+            .seal_detached(&mut FixedNonce::new([9; NONCE_LEN]), b"hello world", b"hdr")
+            .unwrap();
+        assert_eq!(ciphertext.len(), b"hello world".len());
+        assert_eq!(
+            cipher
+                .open_detached(&nonce, &ciphertext, &tag, b"hdr")
+                .unwrap(),
+            b"hello world"
+        );
+
+        // Same bytes as the appended-tag form.
+        let (attached, _) = cipher
+            .encrypt_with_strategy(&mut FixedNonce::new(nonce), b"hello world", b"hdr")
+            .unwrap();
+        assert_eq!(attached, [ciphertext.as_slice(), tag.as_ref()].concat());
+
+        let mut forged = *tag.as_bytes();
+        forged[0] ^= 1;
+        assert_eq!(
+            cipher.open_detached(&nonce, &ciphertext, &Tag::from(forged), b"hdr"),
+            Err(Error::DecryptionFailed)
+        );
+        assert_eq!(Tag::try_from(&forged[1..]), Err(Error::TruncatedInput));
+    }
+
+    #[test]
+    fn test_decrypt_errors_are_distinguished() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let (mut ciphertext, nonce) = encrypt_aes_256_gcm(&key, b"hello world").unwrap();
+
+        assert_eq!(
+            decrypt_aes_256_gcm(&key, &nonce[..8], &ciphertext),
+            Err(Error::InvalidNonce)
+        );
+        assert_eq!(
+            decrypt_aes_256_gcm(&key, &nonce, &ciphertext[..TAG_LEN - 1]),
+            Err(Error::TruncatedInput)
+        );
+
+        ciphertext[0] ^= 1;
+        assert_eq!(
+            decrypt_aes_256_gcm(&key, &nonce, &ciphertext),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_encryption_decryption() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::ChaCha20Poly1305, &key).unwrap();
+
+        let (ciphertext, nonce) = cipher.encrypt_with_aad(b"hello world", b"hdr").unwrap();
+        assert_eq!(ciphertext.len(), b"hello world".len() + TAG_LEN);
+        assert_eq!(
+            cipher
+                .decrypt_with_aad(&nonce, &ciphertext, b"hdr")
+                .unwrap(),
+            b"hello world"
+        );
+
+        // The same key under AES-256-GCM must not open a ChaCha20 ciphertext.
+        let aes = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        assert!(aes.decrypt_with_aad(&nonce, &ciphertext, b"hdr").is_err());
+    }
+
+    #[cfg(feature = "aes192")]
+    #[test]
+    fn test_aes_192_gcm_encryption_decryption() {
+        let key = Key::new(b"twenty-four byte key 192").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes192Gcm, &key).unwrap();
+
+        let (ciphertext, nonce) = cipher.encrypt(b"hello world").expect("encryption failed");
+        assert_eq!(cipher.decrypt(&nonce, &ciphertext).unwrap(), b"hello world");
+    }
+
+    #[cfg(feature = "gcm-siv")]
+    #[test]
+    fn test_aes_256_gcm_siv_matches_rfc_8452() {
+        // RFC 8452, appendix C.2: empty plaintext and AAD.
+        let mut key = [0u8; 32];
+        key[0] = 1;
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[0] = 3;
+        let cipher = Cipher::new(Algorithm::Aes256GcmSiv, &Key::new(&key).unwrap()).unwrap();
+
+        let sealed = cipher.encrypt_with_nonce(nonce, b"", b"").unwrap();
+        assert_eq!(
+            sealed,
+            [
+                0x07, 0xf5, 0xf4, 0x16, 0x9b, 0xbf, 0x55, 0xa8, 0x40, 0x0c, 0xd4, 0x7e, 0xa6, 0xfd,
+                0x40, 0x0f
+            ]
+        );
+        assert_eq!(cipher.decrypt(&nonce, &sealed).unwrap(), b"");
+    }
+
+    #[cfg(feature = "gcm-siv")]
+    #[test]
+    fn test_aes_256_gcm_siv_encryption_decryption() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256GcmSiv, &key).unwrap();
+
+        let (ciphertext, nonce) = cipher.encrypt_with_aad(b"hello world", b"req-1").unwrap();
+        assert_eq!(
+            cipher
+                .decrypt_with_aad(&nonce, &ciphertext, b"req-1")
+                .unwrap(),
+            b"hello world"
+        );
+        assert!(cipher.decrypt(&nonce, &ciphertext).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Async adapters for the [`stream`](crate::stream) format over tokio.
+//!
+//! [`AsyncStreamEncryptor`] and [`AsyncStreamDecryptor`] produce and consume
+//! exactly the same bytes as their blocking counterparts, one segment at a
+//! time, so data can be piped through sockets or files without holding the
+//! whole payload in memory. Either side can be blocking or async.
+
+use std::future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+use crate::aead::{Algorithm, TAG_LEN};
+use crate::error::Error;
+use crate::key::Key;
+use crate::stream::{Segments, DEFAULT_SEGMENT_SIZE, HEADER_LEN};
+
+/// Encrypts everything written to it into the stream format.
+///
+/// Shutting the encryptor down (`AsyncWriteExt::shutdown`) seals the final
+/// segment and then shuts down the inner writer. Use
+/// [`AsyncStreamEncryptor::finish`] instead to keep the writer open. Without
+/// either, the stream is rejected as truncated.
+pub struct AsyncStreamEncryptor<W> {
+    writer: W,
+    segments: Segments,
+    buffer: Vec<u8>,
+    /// Sealed bytes not yet accepted by the writer.
+    pending: Vec<u8>,
+    written: usize,
+    header_written: bool,
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncStreamEncryptor<W> {
+    /// Creates an encryptor with the default segment size.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the encrypted stream.
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `key` - The key; its length must match the algorithm.
+    pub fn new(writer: W, algorithm: Algorithm, key: &Key) -> Result<Self, Error> {
+        Self::with_segment_size(writer, algorithm, key, DEFAULT_SEGMENT_SIZE)
+    }
+
+    /// Creates an encryptor that seals `segment_size` bytes of plaintext per segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the encrypted stream.
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `key` - The key; its length must match the algorithm.
+    /// * `segment_size` - Plaintext bytes per segment, between 1 and `MAX_SEGMENT_SIZE`.
+    pub fn with_segment_size(
+        writer: W,
+        algorithm: Algorithm,
+        key: &Key,
+        segment_size: usize,
+    ) -> Result<Self, Error> {
+        Ok(AsyncStreamEncryptor {
+            writer,
+            segments: Segments::for_encryption(algorithm, key, segment_size)?,
+            buffer: Vec::with_capacity(segment_size),
+            pending: Vec::new(),
+            written: 0,
+            header_written: false,
+            finished: false,
+        })
+    }
+
+    /// Seals the buffered plaintext as the final segment, flushes and
+    /// returns the writer without shutting it down.
+    pub async fn finish(mut self) -> io::Result<W> {
+        future::poll_fn(|cx| self.poll_finish(cx)).await?;
+        Ok(self.writer)
+    }
+
+    fn seal_segment(&mut self, last: bool) -> io::Result<()> {
+        self.pending.clear();
+        self.written = 0;
+        if !self.header_written {
+            self.pending.extend_from_slice(self.segments.header());
+            self.header_written = true;
+        }
+        self.segments.seal(&self.buffer, last, &mut self.pending)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let n =
+                ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_finish(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if !self.finished {
+            ready!(self.poll_drain(cx))?;
+            self.seal_segment(true)?;
+            self.finished = true;
+        }
+        ready!(self.poll_drain(cx))?;
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncStreamEncryptor<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "stream already finished",
+            )));
+        }
+        loop {
+            ready!(this.poll_drain(cx))?;
+            // A full segment is only sealed once more data arrives, since it
+            // may turn out to be the last one.
+            if buf.is_empty() || this.buffer.len() < this.segments.segment_size() {
+                break;
+            }
+            this.seal_segment(false)?;
+        }
+        let take = buf
+            .len()
+            .min(this.segments.segment_size() - this.buffer.len());
+        this.buffer.extend_from_slice(&buf[..take]);
+        Poll::Ready(Ok(take))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_finish(cx))?;
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+/// Decrypts a stream produced by [`AsyncStreamEncryptor`] or
+/// [`StreamEncryptor`](crate::stream::StreamEncryptor).
+///
+/// Segments are authenticated before any of their plaintext is returned. A
+/// read fails with [`io::ErrorKind::InvalidData`] if a segment was modified,
+/// reordered or dropped, or if the stream was truncated.
+pub struct AsyncStreamDecryptor<R> {
+    reader: R,
+    segments: Segments,
+    /// One sealed segment plus a lookahead byte, to tell whether it is the last.
+    sealed: Vec<u8>,
+    filled: usize,
+    plaintext: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncStreamDecryptor<R> {
+    /// Reads the stream header and creates a decryptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the encrypted stream.
+    /// * `key` - The key the stream was encrypted with.
+    pub async fn new(mut reader: R, key: &Key) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        reader
+            .read_exact(&mut header)
+            .await
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => Error::TruncatedInput.into(),
+                _ => e,
+            })?;
+        let segments = Segments::from_header(header, key)?;
+
+        Ok(AsyncStreamDecryptor {
+            reader,
+            sealed: vec![0; segments.segment_size() + TAG_LEN + 1],
+            segments,
+            filled: 0,
+            plaintext: Vec::new(),
+            position: 0,
+            done: false,
+        })
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn poll_open_segment(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let full = self.sealed.len();
+        let mut eof = false;
+        while self.filled < full {
+            let mut buf = ReadBuf::new(&mut self.sealed[self.filled..]);
+            ready!(Pin::new(&mut self.reader).poll_read(cx, &mut buf))?;
+            if buf.filled().is_empty() {
+                eof = true;
+                break;
+            }
+            self.filled += buf.filled().len();
+        }
+
+        // With the lookahead byte present, this segment cannot be the last.
+        let (len, last) = if eof {
+            (self.filled, true)
+        } else {
+            (full - 1, false)
+        };
+        self.plaintext = self.segments.open(&self.sealed[..len], last)?;
+        self.position = 0;
+        self.done = last;
+        if !last {
+            self.sealed[0] = self.sealed[full - 1];
+            self.filled = 1;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncStreamDecryptor<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.position == this.plaintext.len() && !this.done {
+            ready!(this.poll_open_segment(cx))?;
+        }
+        let available = &this.plaintext[this.position..];
+        let n = available.len().min(buf.remaining());
+        buf.put_slice(&available[..n]);
+        this.position += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+    use crate::stream::{StreamDecryptor, StreamEncryptor};
+
+    fn key() -> Key {
+        Key::new(b"an example very very secret key.").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_async_round_trip_and_sync_interop() {
+        let plaintext: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+
+        for len in [0, 1, 1024, 1025, 10_000] {
+            let mut encryptor = AsyncStreamEncryptor::with_segment_size(
+                Vec::new(),
+                Algorithm::Aes256Gcm,
+                &key(),
+                1024,
+            )
+            .unwrap();
+            encryptor.write_all(&plaintext[..len]).await.unwrap();
+            let sealed = encryptor.finish().await.unwrap();
+
+            let mut decryptor = AsyncStreamDecryptor::new(&sealed[..], &key())
+                .await
+                .unwrap();
+            let mut opened = Vec::new();
+            decryptor.read_to_end(&mut opened).await.unwrap();
+            assert_eq!(opened, &plaintext[..len]);
+
+            // The blocking decryptor reads the same format.
+            let mut opened = Vec::new();
+            StreamDecryptor::new(&sealed[..], &key())
+                .unwrap()
+                .read_to_end(&mut opened)
+                .unwrap();
+            assert_eq!(opened, &plaintext[..len]);
+        }
+
+        let mut encryptor = StreamEncryptor::with_segment_size(
+            Vec::new(),
+            Algorithm::ChaCha20Poly1305,
+            &key(),
+            100,
+        )
+        .unwrap();
+        encryptor.write_all(&plaintext).unwrap();
+        let sealed = encryptor.finish().unwrap();
+
+        let mut decryptor = AsyncStreamDecryptor::new(&sealed[..], &key())
+            .await
+            .unwrap();
+        let mut opened = Vec::new();
+        decryptor.read_to_end(&mut opened).await.unwrap();
+        assert_eq!(opened, plaintext);
+    }
+
+    #[tokio::test]
+    async fn test_async_pipe_rejects_truncation() {
+        // A small duplex buffer forces both sides through Poll::Pending.
+        let (writer, reader) = tokio::io::duplex(64);
+        let plaintext: Vec<u8> = (0..5_000).map(|i| i as u8).collect();
+
+        let send = plaintext.clone();
+        let sender = tokio::spawn(async move {
+            let mut encryptor =
+                AsyncStreamEncryptor::with_segment_size(writer, Algorithm::Aes256Gcm, &key(), 256)
+                    .unwrap();
+            encryptor.write_all(&send).await.unwrap();
+            encryptor.shutdown().await.unwrap();
+        });
+
+        let mut decryptor = AsyncStreamDecryptor::new(reader, &key()).await.unwrap();
+        let mut opened = Vec::new();
+        decryptor.read_to_end(&mut opened).await.unwrap();
+        sender.await.unwrap();
+        assert_eq!(opened, plaintext);
+
+        let mut encryptor =
+            AsyncStreamEncryptor::with_segment_size(Vec::new(), Algorithm::Aes256Gcm, &key(), 256)
+                .unwrap();
+        encryptor.write_all(&plaintext).await.unwrap();
+        let sealed = encryptor.finish().await.unwrap();
+
+        // Cut on a segment boundary: the remaining last segment is not final.
+        let cut = HEADER_LEN + 2 * (256 + TAG_LEN);
+        let mut decryptor = AsyncStreamDecryptor::new(&sealed[..cut], &key())
+            .await
+            .unwrap();
+        let err = decryptor.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = AsyncStreamDecryptor::new(&sealed[..HEADER_LEN - 1], &key())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
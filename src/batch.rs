@@ -0,0 +1,223 @@
+//! Batch decryption of many envelopes.
+//!
+//! [`decrypt_batch`] opens every item independently and reports a result per
+//! item, so one corrupt record does not abort a job over millions of rows.
+//! [`decrypt_batch_with_keyring`] does the same for envelopes sealed by a
+//! [`Keyring`], picking each item's key by the id in its header.
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::envelope;
+use crate::error::Error;
+use crate::key::Key;
+use crate::keyring::Keyring;
+
+/// Aggregate counts over a batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /// Number of items that decrypted.
+    pub succeeded: usize,
+    /// Number of items that failed.
+    pub failed: usize,
+    /// Total length in bytes of the recovered plaintexts.
+    pub plaintext_bytes: usize,
+}
+
+/// The outcome of a batch decryption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchOutput {
+    /// One result per input item, in input order.
+    pub results: Vec<Result<Vec<u8>, Error>>,
+    /// Counts over `results`.
+    pub stats: BatchStats,
+}
+
+impl BatchOutput {
+    /// Returns the indices and errors of the items that failed.
+    pub fn failures(&self) -> impl Iterator<Item = (usize, Error)> + '_ {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| result.as_ref().err().map(|err| (index, *err)))
+    }
+
+    fn from_results(results: Vec<Result<Vec<u8>, Error>>) -> BatchOutput {
+        let mut stats = BatchStats::default();
+        for result in &results {
+            match result {
+                Ok(plaintext) => {
+                    stats.succeeded += 1;
+                    stats.plaintext_bytes += plaintext.len();
+                }
+                Err(_) => stats.failed += 1,
+            }
+        }
+        BatchOutput { results, stats }
+    }
+}
+
+/// Decrypts a batch of envelopes sealed with [`envelope::seal`] or
+/// [`envelope::seal_with_subkey`].
+///
+/// # Arguments
+///
+/// * `key` - The key the envelopes were sealed with.
+/// * `items` - The encoded envelopes.
+///
+/// # Returns
+///
+/// A result per item, in input order, and aggregate counts.
+pub fn decrypt_batch<T: AsRef<[u8]>>(key: &Key, items: &[T]) -> BatchOutput {
+    BatchOutput::from_results(open_all(items, &|item| envelope::open(key, item)))
+}
+
+/// Like [`decrypt_batch`], but spreads the items over `threads` worker threads.
+///
+/// # Arguments
+///
+/// * `key` - The key the envelopes were sealed with.
+/// * `items` - The encoded envelopes.
+/// * `threads` - The number of worker threads to use.
+///
+/// # Returns
+///
+/// A result per item, in input order, and aggregate counts.
+pub fn decrypt_batch_parallel<T: AsRef<[u8]> + Sync>(
+    key: &Key,
+    items: &[T],
+    threads: NonZeroUsize,
+) -> BatchOutput {
+    open_parallel(items, threads, &|item| envelope::open(key, item))
+}
+
+/// Decrypts a batch of envelopes sealed by [`Keyring::encrypt`], each with the
+/// key its header names, so a batch may span key rotations.
+///
+/// # Arguments
+///
+/// * `keyring` - The keys the envelopes may have been sealed with.
+/// * `items` - The encoded envelopes.
+///
+/// # Returns
+///
+/// A result per item, in input order, and aggregate counts. Items whose key
+/// is not in the ring fail with `Error::UnknownKeyId`.
+pub fn decrypt_batch_with_keyring<T: AsRef<[u8]>>(keyring: &Keyring, items: &[T]) -> BatchOutput {
+    BatchOutput::from_results(open_all(items, &|item| keyring.decrypt(item)))
+}
+
+/// Like [`decrypt_batch_with_keyring`], but spreads the items over `threads`
+/// worker threads.
+///
+/// # Arguments
+///
+/// * `keyring` - The keys the envelopes may have been sealed with.
+/// * `items` - The encoded envelopes.
+/// * `threads` - The number of worker threads to use.
+///
+/// # Returns
+///
+/// A result per item, in input order, and aggregate counts.
+pub fn decrypt_batch_parallel_with_keyring<T: AsRef<[u8]> + Sync>(
+    keyring: &Keyring,
+    items: &[T],
+    threads: NonZeroUsize,
+) -> BatchOutput {
+    open_parallel(items, threads, &|item| keyring.decrypt(item))
+}
+
+type Open<'a> = dyn Fn(&[u8]) -> Result<Vec<u8>, Error> + Sync + 'a;
+
+fn open_parallel<T: AsRef<[u8]> + Sync>(
+    items: &[T],
+    threads: NonZeroUsize,
+    open: &Open<'_>,
+) -> BatchOutput {
+    let chunk_len = items.len().div_ceil(threads.get()).max(1);
+    let results = thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk_len)
+            .map(|chunk| scope.spawn(move || open_all(chunk, open)))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("batch worker panicked"))
+            .collect()
+    });
+    BatchOutput::from_results(results)
+}
+
+fn open_all<T: AsRef<[u8]>>(items: &[T], open: &Open<'_>) -> Vec<Result<Vec<u8>, Error>> {
+    items.iter().map(|item| open(item.as_ref())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::Algorithm;
+
+    #[test]
+    fn test_batch_reports_partial_failures() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let mut items: Vec<Vec<u8>> = (0..10u8)
+            .map(|i| envelope::seal(Algorithm::Aes256Gcm, &key, &[i; 4]).unwrap())
+            .collect();
+        let last = items[3].len() - 1;
+        items[3][last] ^= 1;
+        items[7].truncate(5);
+
+        let output = decrypt_batch(&key, &items);
+        assert_eq!(
+            output.stats,
+            BatchStats {
+                succeeded: 8,
+                failed: 2,
+                plaintext_bytes: 32
+            }
+        );
+        assert_eq!(
+            output.failures().collect::<Vec<_>>(),
+            [(3, Error::DecryptionFailed), (7, Error::TruncatedInput)]
+        );
+        assert_eq!(output.results[9], Ok(vec![9; 4]));
+
+        let parallel = decrypt_batch_parallel(&key, &items, NonZeroUsize::new(3).unwrap());
+        assert_eq!(parallel, output);
+    }
+
+    #[test]
+    fn test_batch_with_keyring_spans_rotations() {
+        let mut keyring = Keyring::new();
+        keyring
+            .add(
+                b"2026-09",
+                Algorithm::Aes256Gcm,
+                &Key::new(&[1; 32]).unwrap(),
+            )
+            .unwrap();
+        let mut items = vec![keyring.encrypt(b"old").unwrap()];
+        keyring
+            .add(
+                b"2026-10",
+                Algorithm::Aes256Gcm,
+                &Key::new(&[2; 32]).unwrap(),
+            )
+            .unwrap();
+        items.push(keyring.encrypt(b"new").unwrap());
+        items
+            .push(envelope::seal(Algorithm::Aes256Gcm, &Key::new(&[1; 32]).unwrap(), b"").unwrap());
+
+        let output = decrypt_batch_with_keyring(&keyring, &items);
+        assert_eq!(output.results[0], Ok(b"old".to_vec()));
+        assert_eq!(output.results[1], Ok(b"new".to_vec()));
+        assert_eq!(
+            output.failures().collect::<Vec<_>>(),
+            [(2, Error::UnknownKeyId)]
+        );
+
+        let parallel =
+            decrypt_batch_parallel_with_keyring(&keyring, &items, NonZeroUsize::new(2).unwrap());
+        assert_eq!(parallel, output);
+    }
+}
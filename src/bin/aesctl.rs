@@ -0,0 +1,424 @@
+//! `aesctl`: encrypt and decrypt files from the command line.
+//!
+//! With `--key-file`, data is encrypted in the [`stream`] format, so inputs
+//! of any size are processed in constant memory. With `--password-file`,
+//! the whole input is read and sealed with [`encrypt_with_password`]. Input
+//! defaults to stdin and output to stdout, so `aesctl` can sit in a pipe.
+//!
+//! `inspect` prints an [`Envelope`] header without any key, and
+//! `inspect --diff` lists the header fields in which two envelopes differ.
+//!
+//! [`stream`]: aes_implementation::stream
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use aes_implementation::{
+    decrypt_with_password, encrypt_with_password, envelope, fs as crypt_fs, Algorithm, Envelope,
+    Key, StreamDecryptor, StreamEncryptor,
+};
+use ring::rand::SystemRandom;
+use zeroize::Zeroizing;
+
+const USAGE: &str = "\
+usage: aesctl keygen [--algorithm NAME] [-o KEY_FILE]
+       aesctl encrypt (--key-file FILE [--algorithm NAME] | --password-file FILE) [INPUT] [-o OUTPUT]
+       aesctl decrypt (--key-file FILE | --password-file FILE) [INPUT] [-o OUTPUT]
+       aesctl inspect [ENVELOPE | --diff A B]
+
+INPUT and OUTPUT default to stdin and stdout; `-` also means either.
+Decrypted output written to stdout may be cut short if authentication
+fails part-way; check the exit status. An OUTPUT file is only replaced
+once the whole input has been processed.
+keygen creates KEY_FILE readable by its owner only, and never replaces
+an existing file.
+inspect reads envelopes sealed with the library's envelope API, not
+encrypted streams, and needs no key.
+Algorithms: AES-128-GCM, AES-192-GCM, AES-256-GCM (default),
+AES-256-GCM-SIV, ChaCha20-Poly1305.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Keygen,
+    Encrypt,
+    Decrypt,
+    Inspect,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Secret {
+    KeyFile(PathBuf),
+    PasswordFile(PathBuf),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    command: Command,
+    secret: Option<Secret>,
+    algorithm: Algorithm,
+    input: Option<PathBuf>,
+    output: Option<PathBuf>,
+    diff: Option<[PathBuf; 2]>,
+}
+
+/// Parses the arguments after the program name.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        Some("keygen") => Command::Keygen,
+        Some("encrypt") => Command::Encrypt,
+        Some("decrypt") => Command::Decrypt,
+        Some("inspect") => Command::Inspect,
+        Some(other) => return Err(format!("unknown command `{other}`")),
+        None => return Err("missing command".to_string()),
+    };
+
+    let mut options = Options {
+        command,
+        secret: None,
+        algorithm: Algorithm::Aes256Gcm,
+        input: None,
+        output: None,
+        diff: None,
+    };
+    let mut algorithm_given = false;
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{flag} needs a value"));
+        match arg.as_str() {
+            "--key-file" | "--password-file" if options.secret.is_some() => {
+                return Err("give only one of --key-file and --password-file".to_string());
+            }
+            "--key-file" => options.secret = Some(Secret::KeyFile(value(&arg)?.into())),
+            "--password-file" => options.secret = Some(Secret::PasswordFile(value(&arg)?.into())),
+            "--algorithm" => {
+                options.algorithm = parse_algorithm(&value(&arg)?)?;
+                algorithm_given = true;
+            }
+            "-o" | "--output" => options.output = path(value(&arg)?),
+            "--diff" if command == Command::Inspect => {
+                options.diff = Some([value(&arg)?.into(), value(&arg)?.into()]);
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option `{flag}`"));
+            }
+            _ if options.input.is_some()
+                || options.diff.is_some()
+                || command == Command::Keygen =>
+            {
+                return Err(format!("unexpected argument `{arg}`"));
+            }
+            _ => options.input = path(arg),
+        }
+    }
+
+    if options.diff.is_some() && options.input.is_some() {
+        return Err("inspect --diff takes no other ENVELOPE".to_string());
+    }
+    match (command, &options.secret) {
+        (Command::Keygen | Command::Inspect, Some(_)) => {
+            return Err("keygen and inspect take no --key-file or --password-file".to_string())
+        }
+        (Command::Encrypt | Command::Decrypt, None) => {
+            return Err("--key-file or --password-file is required".to_string())
+        }
+        _ => {}
+    }
+    // Decryption reads the algorithm from the header, and password mode
+    // always uses AES-256-GCM.
+    if algorithm_given
+        && (matches!(command, Command::Decrypt | Command::Inspect)
+            || matches!(options.secret, Some(Secret::PasswordFile(_))))
+    {
+        return Err("--algorithm only applies to keygen and --key-file encryption".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_algorithm(name: &str) -> Result<Algorithm, String> {
+    Algorithm::ALL
+        .into_iter()
+        .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+        .ok_or(format!("unknown algorithm `{name}`"))
+}
+
+/// Maps `-` to `None`, meaning stdin or stdout.
+fn path(arg: String) -> Option<PathBuf> {
+    (arg != "-").then(|| arg.into())
+}
+
+/// Runs a parsed command, reading from `stdin` and writing to `stdout`
+/// wherever no file was given.
+fn run(options: &Options, stdin: &mut dyn Read, stdout: &mut dyn Write) -> io::Result<()> {
+    match &options.secret {
+        None if options.command == Command::Inspect => inspect(options, stdin, stdout),
+        None => {
+            let key = Key::generate_for(options.algorithm, &SystemRandom::new())?;
+            write_key(options, stdout, key.as_bytes())
+        }
+        Some(Secret::KeyFile(key_file)) => {
+            let key = Key::new(&Zeroizing::new(fs::read(key_file)?))?;
+            run_with_key(options, &key, stdin, stdout)
+        }
+        Some(Secret::PasswordFile(password_file)) => {
+            let password = read_password(password_file)?;
+            let mut input = Zeroizing::new(Vec::new());
+            open_input(options, stdin)?.read_to_end(&mut input)?;
+            let output = match options.command {
+                Command::Encrypt => Zeroizing::new(encrypt_with_password(&password, &input)?),
+                _ => Zeroizing::new(decrypt_with_password(&password, &input)?),
+            };
+            write_output(options, stdout, &output)
+        }
+    }
+}
+
+fn run_with_key(
+    options: &Options,
+    key: &Key,
+    stdin: &mut dyn Read,
+    stdout: &mut dyn Write,
+) -> io::Result<()> {
+    let mut input = open_input(options, stdin)?;
+    let mut transform = |output: &mut dyn Write| match options.command {
+        Command::Encrypt => {
+            let mut encryptor =
+                StreamEncryptor::new(BufWriter::new(output), options.algorithm, key)?;
+            io::copy(&mut input, &mut encryptor)?;
+            encryptor.finish()?.flush()
+        }
+        _ => {
+            let mut decryptor = StreamDecryptor::new(&mut input, key)?;
+            let mut output = BufWriter::new(output);
+            io::copy(&mut decryptor, &mut output)?;
+            output.flush()
+        }
+    };
+    match &options.output {
+        // Unauthenticated plaintext never reaches the destination path.
+        Some(path) => crypt_fs::write_atomically(path, |file| transform(file)),
+        None => transform(stdout),
+    }
+}
+
+/// Prints one envelope header, or the differences between two.
+fn inspect(options: &Options, stdin: &mut dyn Read, stdout: &mut dyn Write) -> io::Result<()> {
+    let mut report = String::new();
+    if let Some([a, b]) = &options.diff {
+        let differences = envelope::diff(&fs::read(a)?, &fs::read(b)?)?;
+        if differences.is_empty() {
+            report.push_str("headers match\n");
+        }
+        for difference in differences {
+            report += &format!("{difference}\n");
+        }
+    } else {
+        let mut blob = Vec::new();
+        open_input(options, stdin)?.read_to_end(&mut blob)?;
+        let envelope = Envelope::from_bytes(&blob)?;
+        let header = envelope.header();
+        let key_id = header.key_id().map_or("none".into(), |id| {
+            String::from_utf8_lossy(id.as_bytes()).into_owned()
+        });
+        report = format!(
+            "version: {}\nalgorithm: {}\nkey id: {key_id}\nper-message subkey: {}\n\
+             cascade: {}\nciphertext: {} bytes\n",
+            header.version(),
+            header.algorithm().name(),
+            header.subkey().is_some(),
+            header.is_cascade(),
+            envelope.ciphertext().len(),
+        );
+    }
+    write_output(options, stdout, report.as_bytes())
+}
+
+fn open_input<'a>(options: &Options, stdin: &'a mut dyn Read) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match &options.input {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(stdin),
+    })
+}
+
+fn write_output(options: &Options, stdout: &mut dyn Write, data: &[u8]) -> io::Result<()> {
+    match &options.output {
+        Some(path) => crypt_fs::write_atomically(path, |file| file.write_all(data)),
+        None => stdout.write_all(data).and_then(|()| stdout.flush()),
+    }
+}
+
+/// Writes a new key to a file only the owner can read, refusing to replace
+/// an existing one.
+fn write_key(options: &Options, stdout: &mut dyn Write, key: &[u8]) -> io::Result<()> {
+    let Some(path) = &options.output else {
+        return stdout.write_all(key).and_then(|()| stdout.flush());
+    };
+    let mut open = OpenOptions::new();
+    open.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut open, 0o600);
+    let mut file = open.open(path)?;
+    file.write_all(key)?;
+    file.sync_all()
+}
+
+/// Reads a password file, dropping one trailing line ending.
+fn read_password(path: &Path) -> io::Result<Zeroizing<Vec<u8>>> {
+    let mut password = Zeroizing::new(fs::read(path)?);
+    if password.ends_with(b"\n") {
+        password.pop();
+        if password.ends_with(b"\r") {
+            password.pop();
+        }
+    }
+    Ok(password)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("aesctl: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options, &mut io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("aesctl: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Options, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            args("encrypt --key-file k.bin input.txt -o input.enc").unwrap(),
+            Options {
+                command: Command::Encrypt,
+                secret: Some(Secret::KeyFile("k.bin".into())),
+                algorithm: Algorithm::Aes256Gcm,
+                input: Some("input.txt".into()),
+                output: Some("input.enc".into()),
+                diff: None,
+            }
+        );
+        let keygen = args("keygen --algorithm aes-128-gcm -o -").unwrap();
+        assert_eq!(keygen.algorithm, Algorithm::Aes128Gcm);
+        assert_eq!(keygen.output, None);
+
+        assert!(args("").is_err());
+        assert!(args("encrypt input.txt").is_err());
+        assert!(args("encrypt --key-file k --password-file p").is_err());
+        assert!(args("decrypt --key-file k --algorithm AES-256-GCM").is_err());
+        assert!(args("encrypt --key-file k a b").is_err());
+        assert!(args("keygen --algorithm rot13").is_err());
+        assert_eq!(
+            args("inspect --diff a b").unwrap().diff,
+            Some(["a".into(), "b".into()])
+        );
+        assert!(args("inspect --diff a").is_err());
+        assert!(args("inspect a --diff b c").is_err());
+        assert!(args("inspect --key-file k a").is_err());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let dir = env::temp_dir().join(format!("aesctl-inspect-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let key = Key::new(&[1; 32]).unwrap();
+        let a = dir.join("a.bin");
+        let b = dir.join("b.bin");
+        fs::write(
+            &a,
+            envelope::seal(Algorithm::Aes256Gcm, &key, b"hello").unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            &b,
+            envelope::seal(Algorithm::ChaCha20Poly1305, &key, b"").unwrap(),
+        )
+        .unwrap();
+
+        let mut report = Vec::new();
+        let inspect = args("inspect -").unwrap();
+        run(&inspect, &mut &fs::read(&a).unwrap()[..], &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("algorithm: AES-256-GCM\n"));
+        assert!(report.contains("key id: none\n"));
+        assert!(report.contains("ciphertext: 5 bytes\n"));
+
+        let mut report = Vec::new();
+        let diff = args(&format!("inspect --diff {} {}", a.display(), b.display())).unwrap();
+        run(&diff, &mut io::empty(), &mut report).unwrap();
+        assert_eq!(report, b"algorithm: AES-256-GCM vs ChaCha20-Poly1305\n");
+
+        let mut report = Vec::new();
+        let same = args(&format!("inspect --diff {} {}", a.display(), a.display())).unwrap();
+        run(&same, &mut io::empty(), &mut report).unwrap();
+        assert_eq!(report, b"headers match\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pipe_round_trip() {
+        let dir = env::temp_dir().join(format!("aesctl-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("k.bin");
+        let password_file = dir.join("password.txt");
+        fs::write(&password_file, "correct horse battery staple\n").unwrap();
+
+        let keygen = args(&format!("keygen -o {}", key_file.display())).unwrap();
+        run(&keygen, &mut io::empty(), &mut io::sink()).unwrap();
+        assert_eq!(fs::read(&key_file).unwrap().len(), 32);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&key_file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let err = run(&keygen, &mut io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        for secret in [
+            format!("--key-file {}", key_file.display()),
+            format!("--password-file {}", password_file.display()),
+        ] {
+            let mut sealed = Vec::new();
+            let encrypt = args(&format!("encrypt {secret}")).unwrap();
+            run(&encrypt, &mut &b"hello world"[..], &mut sealed).unwrap();
+            assert!(!sealed.windows(11).any(|window| window == b"hello world"));
+
+            let mut opened = Vec::new();
+            let decrypt = args(&format!("decrypt {secret} -")).unwrap();
+            run(&decrypt, &mut &sealed[..], &mut opened).unwrap();
+            assert_eq!(opened, b"hello world");
+
+            let last = sealed.len() - 1;
+            sealed[last] ^= 1;
+            let err = run(&decrypt, &mut &sealed[..], &mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // Nothing is left behind at the destination either.
+            let output = dir.join("output.txt");
+            let decrypt = args(&format!("decrypt {secret} -o {}", output.display())).unwrap();
+            assert!(run(&decrypt, &mut &sealed[..], &mut io::sink()).is_err());
+            assert!(!output.exists());
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,519 @@
+//! Root key ceremonies: generation, Shamir key shares and a signed record.
+//!
+//! [`generate`] draws a fresh 256-bit root key and splits it into `count`
+//! shares, any `threshold` of which recover it with [`combine`]. Fewer shares
+//! reveal nothing about the key. Each share carries a checksum so custodians
+//! can confirm a transcription, and the [`CeremonyRecord`] lists the key
+//! fingerprint and every share checksum without containing any secret. The
+//! officer running the ceremony signs the record with an Ed25519 key, so it
+//! can later be checked that a recovered key is the one that was attested.
+//!
+//! ```
+//! use aes_implementation::ceremony::{self, Share};
+//!
+//! let ceremony = ceremony::generate("root-2026", 3, 5).unwrap();
+//! let texts: Vec<String> = ceremony.shares.iter().map(Share::to_text).collect();
+//! println!("{}", ceremony.record);
+//!
+//! // Any three custodians can recover the key.
+//! let shares: Vec<Share> = texts[1..4].iter().map(|t| Share::from_text(t).unwrap()).collect();
+//! let key = ceremony::combine(&shares).unwrap();
+//! assert!(ceremony.record.matches(&key));
+//! ```
+//!
+//! Shares are computed byte-wise over GF(2^8) with the AES reduction
+//! polynomial, at the x coordinates 1 to `count`.
+
+use alloc::{borrow::ToOwned, format, string::String, string::ToString, vec, vec::Vec};
+use core::fmt;
+
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use zeroize::Zeroizing;
+
+use crate::encoding;
+use crate::error::Error;
+use crate::key::Key;
+use crate::rng;
+
+const FINGERPRINT_LABEL: &[u8] = b"aes_implementation/fingerprint";
+const SHARE_PREFIX: &str = "share";
+const RECORD_TITLE: &str = "aes_implementation key ceremony v1";
+
+/// One custodian's share of a root key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    index: u8,
+    threshold: u8,
+    data: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    /// Returns the share's x coordinate, between 1 and the share count.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the number of shares needed to recover the key.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the checksum listed for this share in the ceremony record.
+    pub fn checksum(&self) -> String {
+        let mut input = vec![self.index, self.threshold];
+        input.extend_from_slice(&self.data);
+        let hash = digest(&SHA256, &input);
+        encoding::encode_hex(&hash.as_ref()[..4])
+    }
+
+    /// Encodes the share for a custodian, as
+    /// `share-<threshold>-<index>-<data hex>-<checksum>`.
+    pub fn to_text(&self) -> String {
+        format!(
+            "{SHARE_PREFIX}-{}-{}-{}-{}",
+            self.threshold,
+            self.index,
+            encoding::encode_hex(&self.data),
+            self.checksum()
+        )
+    }
+
+    /// Decodes a share produced by [`Share::to_text`].
+    ///
+    /// # Returns
+    ///
+    /// The share, `Error::InvalidEncoding` if the text is malformed or its
+    /// checksum does not match, e.g. because of a typo, or
+    /// `Error::InvalidShares` if its threshold or index is zero.
+    ///
+    /// The checksum only catches typos: it is unkeyed, so anyone can write
+    /// a share that passes it.
+    pub fn from_text(text: &str) -> Result<Share, Error> {
+        let fields: Vec<&str> = text.trim().split('-').collect();
+        let [SHARE_PREFIX, threshold, index, data, checksum] = fields[..] else {
+            return Err(Error::InvalidEncoding);
+        };
+        let share = Share {
+            threshold: threshold.parse().map_err(|_| Error::InvalidEncoding)?,
+            index: index.parse().map_err(|_| Error::InvalidEncoding)?,
+            data: Zeroizing::new(encoding::decode_hex(data)?),
+        };
+        if share.checksum() != checksum.to_ascii_lowercase() {
+            return Err(Error::InvalidEncoding);
+        }
+        if share.threshold == 0 || share.index == 0 {
+            return Err(Error::InvalidShares);
+        }
+        Ok(share)
+    }
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("index", &self.index)
+            .field("threshold", &self.threshold)
+            .field("checksum", &self.checksum())
+            .finish()
+    }
+}
+
+/// The public outcome of a ceremony: no secret material, safe to archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CeremonyRecord {
+    label: String,
+    threshold: u8,
+    key_fingerprint: String,
+    share_checksums: Vec<String>,
+}
+
+impl CeremonyRecord {
+    /// Returns the label the ceremony was run under.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the number of shares needed to recover the key.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the fingerprint of the root key, see [`fingerprint`].
+    pub fn key_fingerprint(&self) -> &str {
+        &self.key_fingerprint
+    }
+
+    /// Returns the checksum of every share, in share index order.
+    pub fn share_checksums(&self) -> &[String] {
+        &self.share_checksums
+    }
+
+    /// Returns whether `key` is the root key this record attests.
+    pub fn matches(&self, key: &Key) -> bool {
+        fingerprint(key) == self.key_fingerprint
+    }
+
+    /// Signs the record's text form.
+    ///
+    /// # Arguments
+    ///
+    /// * `signer` - The ceremony officer's Ed25519 key pair.
+    pub fn sign(&self, signer: &Ed25519KeyPair) -> SignedRecord {
+        let signature = signer.sign(self.to_string().as_bytes());
+        SignedRecord {
+            record: self.clone(),
+            public_key: signer.public_key().as_ref().to_vec(),
+            signature: signature.as_ref().to_vec(),
+        }
+    }
+}
+
+/// The text form of the record, which is what gets signed.
+impl fmt::Display for CeremonyRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{RECORD_TITLE}")?;
+        writeln!(f, "label: {}", self.label)?;
+        writeln!(f, "key fingerprint: {}", self.key_fingerprint)?;
+        writeln!(
+            f,
+            "threshold: {} of {}",
+            self.threshold,
+            self.share_checksums.len()
+        )?;
+        for (index, checksum) in self.share_checksums.iter().enumerate() {
+            writeln!(f, "share {} checksum: {checksum}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// A ceremony record with the officer's signature over its text form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRecord {
+    record: CeremonyRecord,
+    public_key: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl SignedRecord {
+    /// Returns the signed record.
+    pub fn record(&self) -> &CeremonyRecord {
+        &self.record
+    }
+
+    /// Returns the signer's Ed25519 public key.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Returns the Ed25519 signature.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Checks the signature against the officer's known public key.
+    ///
+    /// # Returns
+    ///
+    /// `Error::InvalidSignature` if the record was signed by another key or
+    /// modified after signing.
+    pub fn verify(&self, public_key: &[u8]) -> Result<(), Error> {
+        if public_key != self.public_key.as_slice() {
+            return Err(Error::InvalidSignature);
+        }
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(self.record.to_string().as_bytes(), &self.signature)
+            .map_err(|_| Error::InvalidSignature)
+    }
+}
+
+/// Appends the public key and signature to the record's text form.
+impl fmt::Display for SignedRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.record)?;
+        writeln!(f, "public key: {}", encoding::encode_hex(&self.public_key))?;
+        writeln!(f, "signature: {}", encoding::encode_hex(&self.signature))
+    }
+}
+
+/// The result of [`generate`]: the root key, its shares and the record.
+pub struct Ceremony {
+    /// The root key. Drop it once the shares are handed out.
+    pub key: Key,
+    /// One share per custodian, in index order.
+    pub shares: Vec<Share>,
+    /// The record to sign and archive.
+    pub record: CeremonyRecord,
+}
+
+/// Generates a 256-bit root key and splits it into shares.
+///
+/// # Arguments
+///
+/// * `label` - A name for the key, recorded in the ceremony record. It may
+///   not contain control characters.
+/// * `threshold` - The number of shares needed to recover the key.
+/// * `count` - The number of shares to create.
+///
+/// # Returns
+///
+/// The ceremony outcome, `Error::InvalidShares` unless
+/// `1 <= threshold <= count`, or `Error::InvalidEncoding` for a bad label.
+pub fn generate(label: &str, threshold: u8, count: u8) -> Result<Ceremony, Error> {
+    if label.chars().any(char::is_control) {
+        return Err(Error::InvalidEncoding);
+    }
+    let key = Key::generate(&SystemRandom::new())?;
+    let shares = split(&key, threshold, count)?;
+    let record = CeremonyRecord {
+        label: label.to_owned(),
+        threshold,
+        key_fingerprint: fingerprint(&key),
+        share_checksums: shares.iter().map(Share::checksum).collect(),
+    };
+    Ok(Ceremony {
+        key,
+        shares,
+        record,
+    })
+}
+
+/// Splits a key into `count` shares, any `threshold` of which recover it.
+///
+/// # Returns
+///
+/// The shares in index order, or `Error::InvalidShares` unless
+/// `1 <= threshold <= count`.
+pub fn split(key: &Key, threshold: u8, count: u8) -> Result<Vec<Share>, Error> {
+    if threshold == 0 || threshold > count {
+        return Err(Error::InvalidShares);
+    }
+    let secret = key.as_bytes();
+
+    // One polynomial per key byte, with the key byte as constant term.
+    let degree = threshold as usize - 1;
+    let mut coefficients = Zeroizing::new(vec![0u8; secret.len() * degree]);
+    rng::fill(&mut coefficients)?;
+
+    let shares = (1..=count)
+        .map(|x| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(i, constant)| {
+                    // Horner's rule, highest degree first.
+                    coefficients[i * degree..][..degree]
+                        .iter()
+                        .rev()
+                        .chain([constant])
+                        .fold(0, |acc, &c| gf_mul(acc, x) ^ c)
+                })
+                .collect();
+            Share {
+                index: x,
+                threshold,
+                data: Zeroizing::new(data),
+            }
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Recovers a key from at least `threshold` of its shares.
+///
+/// # Returns
+///
+/// The key, or `Error::InvalidShares` if the shares are fewer than their
+/// threshold, duplicated, or disagree on the threshold or share length.
+///
+/// Shares from two different splits with the same threshold are not
+/// detected: they combine into a wrong key without an error. Check the
+/// result against the ceremony record with [`CeremonyRecord::matches`].
+pub fn combine(shares: &[Share]) -> Result<Key, Error> {
+    let first = shares.first().ok_or(Error::InvalidShares)?;
+    let threshold = first.threshold as usize;
+    let len = first.data.len();
+    // A zero threshold would interpolate no shares at all into a zero key.
+    if threshold == 0
+        || shares.len() < threshold
+        || shares.iter().any(|share| {
+            share.index == 0 || share.threshold != first.threshold || share.data.len() != len
+        })
+    {
+        return Err(Error::InvalidShares);
+    }
+    let shares = &shares[..threshold];
+    for (i, share) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(Error::InvalidShares);
+        }
+    }
+
+    // Lagrange interpolation at x = 0; subtraction is XOR in GF(2^8).
+    let mut secret = Zeroizing::new(vec![0u8; len]);
+    for share in shares {
+        let mut basis = 1;
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+        }
+        for (out, &y) in secret.iter_mut().zip(share.data.iter()) {
+            *out ^= gf_mul(y, basis);
+        }
+    }
+    Key::from_bytes(&secret).map_err(|_| Error::InvalidShares)
+}
+
+/// Returns a fingerprint that identifies a key without revealing it: the
+/// first 16 bytes of a labelled SHA-256 hash, as colon-separated hex groups.
+pub fn fingerprint(key: &Key) -> String {
+    let mut input = Zeroizing::new(FINGERPRINT_LABEL.to_vec());
+    input.extend_from_slice(key.as_bytes());
+    let hash = digest(&SHA256, &input);
+    hash.as_ref()[..16]
+        .chunks(2)
+        .map(encoding::encode_hex)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    // Constant-time: no branches or lookups on secret values.
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b in GF(2^8).
+    let mut inverse = 1;
+    let mut power = b;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            inverse = gf_mul(inverse, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_subset_recovers_the_key() {
+        let ceremony = generate("root-2026", 3, 5).unwrap();
+        let shares = &ceremony.shares;
+
+        for (a, b, c) in [(0, 1, 2), (0, 2, 4), (4, 3, 1), (1, 2, 3)] {
+            let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+            assert_eq!(combine(&subset).unwrap(), ceremony.key);
+        }
+        assert!(ceremony.record.matches(&combine(&shares[1..]).unwrap()));
+
+        assert_eq!(combine(&shares[..2]), Err(Error::InvalidShares));
+        let duplicated = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert_eq!(combine(&duplicated), Err(Error::InvalidShares));
+
+        let other = generate("other", 2, 3).unwrap();
+        let mixed = [
+            shares[0].clone(),
+            shares[1].clone(),
+            other.shares[0].clone(),
+        ];
+        assert_eq!(combine(&mixed), Err(Error::InvalidShares));
+
+        assert!(matches!(generate("x", 0, 3), Err(Error::InvalidShares)));
+        assert!(matches!(generate("x", 4, 3), Err(Error::InvalidShares)));
+        assert!(matches!(
+            generate("a\nb", 2, 3),
+            Err(Error::InvalidEncoding)
+        ));
+    }
+
+    #[test]
+    fn test_share_text_detects_typos() {
+        let ceremony = generate("root-2026", 2, 2).unwrap();
+        let text = ceremony.shares[1].to_text();
+        assert!(text.starts_with("share-2-2-"));
+        assert_eq!(Share::from_text(&text).unwrap(), ceremony.shares[1]);
+        assert_eq!(
+            Share::from_text(&text).unwrap().checksum(),
+            ceremony.record.share_checksums()[1]
+        );
+
+        let typo = text.replacen("share-2-2-", "share-2-1-", 1);
+        assert_eq!(Share::from_text(&typo), Err(Error::InvalidEncoding));
+        assert_eq!(Share::from_text("share-2-2"), Err(Error::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_rejects_forged_zero_threshold() {
+        let forged = Share {
+            threshold: 0,
+            index: 1,
+            data: Zeroizing::new(vec![0x55; 32]),
+        };
+        assert_eq!(
+            combine(std::slice::from_ref(&forged)).err(),
+            Some(Error::InvalidShares)
+        );
+        assert_eq!(
+            Share::from_text(&forged.to_text()),
+            Err(Error::InvalidShares)
+        );
+
+        let zero_index = Share {
+            threshold: 1,
+            index: 0,
+            ..forged
+        };
+        assert_eq!(
+            Share::from_text(&zero_index.to_text()),
+            Err(Error::InvalidShares)
+        );
+    }
+
+    #[test]
+    fn test_signed_record() {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let officer = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        let ceremony = generate("root-2026", 2, 3).unwrap();
+        let signed = ceremony.record.sign(&officer);
+        signed.verify(signed.public_key()).unwrap();
+
+        let text = signed.to_string();
+        assert!(text.starts_with("aes_implementation key ceremony v1\nlabel: root-2026\n"));
+        assert!(text.contains("threshold: 2 of 3\n"));
+        assert!(text.contains(ceremony.record.key_fingerprint()));
+        assert!(!text.contains(&encoding::encode_hex(ceremony.key.as_bytes())));
+
+        let mut forged = signed.clone();
+        forged.record.threshold = 1;
+        assert_eq!(
+            forged.verify(signed.public_key()),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(signed.verify(&[0; 32]), Err(Error::InvalidSignature));
+    }
+
+    #[test]
+    fn test_gf_arithmetic() {
+        // FIPS 197 section 4.2: {57} * {83} = {c1}.
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(gf_div(1, a), a), 1);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Deterministic encryption for fields that must be searchable.
+//!
+//! Every other API in this crate is randomized: sealing the same plaintext
+//! twice gives unrelated ciphertexts. A [`DeterministicCipher`] instead maps
+//! equal plaintexts with equal associated data to equal ciphertexts, so an
+//! encrypted database column can still be indexed and queried for equality.
+//!
+//! The nonce is synthetic: the first 12 bytes of an HMAC-SHA256 over the
+//! associated data and plaintext, under a key derived separately from the
+//! encryption key. The output is laid out as:
+//!
+//! ```text
+//! offset  size  field
+//! 0       12    synthetic nonce
+//! 12      ..    AES-256-GCM ciphertext
+//! ..      16    authentication tag
+//! ```
+//!
+//! **Equality is not hidden.** Anyone who sees two ciphertexts learns whether
+//! the plaintexts are equal, and can count how often each value occurs. Only
+//! use this for columns that need equality lookups, pass the table and
+//! column name as associated data so equal values in different columns do not
+//! match, and prefer [`envelope`](crate::envelope) everywhere else.
+//!
+//! Encrypt at most 2^32 distinct values under one key; beyond that, two of
+//! them may share a synthetic nonce.
+
+use alloc::vec::Vec;
+
+use ring::{constant_time, hmac};
+
+use crate::aead::{Algorithm, Cipher, NONCE_LEN, TAG_LEN};
+use crate::error::Error;
+use crate::kdf;
+use crate::key::Key;
+
+/// Encrypts fields so that equal inputs give equal ciphertexts.
+pub struct DeterministicCipher {
+    cipher: Cipher,
+    nonce_key: hmac::Key,
+}
+
+impl DeterministicCipher {
+    /// Creates a deterministic cipher.
+    ///
+    /// # Arguments
+    ///
+    /// * `master` - A 256-bit key; the encryption and nonce keys are derived
+    ///   from it with HKDF-SHA256.
+    ///
+    /// # Returns
+    ///
+    /// The cipher, or `Error::InvalidKeyLength` if the key is not 32 bytes.
+    pub fn new(master: &Key) -> Result<DeterministicCipher, Error> {
+        let (encryption_key, nonce_key) = kdf::derive_deterministic_keys(master)?;
+        Ok(DeterministicCipher {
+            cipher: Cipher::new(Algorithm::Aes256Gcm, &encryption_key)?,
+            nonce_key: hmac::Key::new(hmac::HMAC_SHA256, nonce_key.as_bytes()),
+        })
+    }
+
+    /// Encrypts one field.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The data to encrypt.
+    /// * `aad` - Associated data, e.g. the table and column name; equal
+    ///   plaintexts only give equal ciphertexts under equal associated data.
+    ///
+    /// # Returns
+    ///
+    /// The ciphertext laid out as in the module documentation.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.synthetic_nonce(plaintext, aad);
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&self.cipher.encrypt_with_nonce(nonce, plaintext, aad)?);
+        Ok(blob)
+    }
+
+    /// Decrypts one field.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The ciphertext returned by [`DeterministicCipher::seal`].
+    /// * `aad` - The associated data passed at encryption time.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext, `Error::TruncatedInput` if the blob is too
+    /// short, or `Error::DecryptionFailed` if the key or associated data is
+    /// wrong or the blob was modified.
+    pub fn open(&self, blob: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        if blob.len() < NONCE_LEN + TAG_LEN {
+            return Err(Error::TruncatedInput);
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt_with_aad(nonce, ciphertext, aad)?;
+
+        // The tag already authenticates the nonce; this also rejects blobs
+        // sealed with some other nonce, which would not match a lookup.
+        constant_time::verify_slices_are_equal(nonce, &self.synthetic_nonce(&plaintext, aad))
+            .map_err(|_| Error::DecryptionFailed)?;
+        Ok(plaintext)
+    }
+
+    fn synthetic_nonce(&self, plaintext: &[u8], aad: &[u8]) -> [u8; NONCE_LEN] {
+        let mut context = hmac::Context::with_key(&self.nonce_key);
+        context.update(&(aad.len() as u64).to_be_bytes());
+        context.update(aad);
+        context.update(plaintext);
+        let tag = context.sign();
+        tag.as_ref()[..NONCE_LEN].try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_inputs_give_equal_ciphertexts() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = DeterministicCipher::new(&key).unwrap();
+
+        let email = cipher.seal(b"alice@example.com", b"users.email").unwrap();
+        assert_eq!(email.len(), NONCE_LEN + 17 + TAG_LEN);
+        assert_eq!(
+            cipher.seal(b"alice@example.com", b"users.email").unwrap(),
+            email
+        );
+        assert_eq!(
+            cipher.open(&email, b"users.email").unwrap(),
+            b"alice@example.com"
+        );
+
+        // Other plaintexts, columns and keys give unrelated ciphertexts.
+        assert_ne!(
+            cipher.seal(b"bob@example.com", b"users.email").unwrap()[..NONCE_LEN],
+            email[..NONCE_LEN]
+        );
+        assert_ne!(
+            cipher.seal(b"alice@example.com", b"users.name").unwrap(),
+            email
+        );
+        let other = DeterministicCipher::new(&Key::new(&[7; 32]).unwrap()).unwrap();
+        assert_ne!(
+            other.seal(b"alice@example.com", b"users.email").unwrap(),
+            email
+        );
+
+        assert_eq!(
+            cipher.open(&email, b"users.name"),
+            Err(Error::DecryptionFailed)
+        );
+        assert_eq!(
+            cipher.open(&email[..27], b"users.email"),
+            Err(Error::TruncatedInput)
+        );
+        assert_eq!(
+            DeterministicCipher::new(&Key::new(&[7; 16]).unwrap()).err(),
+            Some(Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_foreign_nonce() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = DeterministicCipher::new(&key).unwrap();
+
+        // A valid AES-256-GCM ciphertext under the right key, but with a nonce
+        // that is not the synthetic one.
+        let nonce = [9; NONCE_LEN];
+        let forged = [
+            &nonce[..],
+            &cipher
+                .cipher
+                .encrypt_with_nonce(nonce, b"alice", b"")
+                .unwrap(),
+        ]
+        .concat();
+        assert_eq!(cipher.open(&forged, b""), Err(Error::DecryptionFailed));
+    }
+}
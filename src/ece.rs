@@ -0,0 +1,322 @@
+//! Encrypted Content-Encoding for HTTP (RFC 8188, `aes128gcm`).
+//!
+//! The payload starts with a header (`salt || rs || idlen || keyid`) followed
+//! by a sequence of AES-128-GCM records of exactly `rs` bytes each, except for
+//! the last one which may be shorter. Each record is padded with a delimiter
+//! octet (`0x01` for intermediate records, `0x02` for the final one) so that
+//! truncation on a record boundary is detected.
+
+use alloc::vec::Vec;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_128_GCM, NONCE_LEN};
+use ring::hkdf::{Salt, HKDF_SHA256};
+
+use crate::aead::TAG_LEN;
+use crate::error::Error;
+use crate::kdf::OkmLen;
+use crate::nonce::XorNonce;
+use crate::rng;
+
+/// Length of the random salt at the start of every payload.
+pub const SALT_LEN: usize = 16;
+
+/// Record size used by [`encrypt`] when the caller has no preference.
+pub const DEFAULT_RECORD_SIZE: u32 = 4096;
+
+const FIXED_HEADER_LEN: usize = SALT_LEN + 4 + 1;
+// A record must hold at least one delimiter octet plus the tag.
+const MIN_RECORD_SIZE: u32 = TAG_LEN as u32 + 2;
+
+const CEK_INFO: &[u8] = b"Content-Encoding: aes128gcm\0";
+const NONCE_INFO: &[u8] = b"Content-Encoding: nonce\0";
+
+const DELIMITER: u8 = 0x01;
+const LAST_DELIMITER: u8 = 0x02;
+
+/// The header preceding the records of an `aes128gcm` payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// The salt mixed into the content-encryption key derivation.
+    pub salt: [u8; SALT_LEN],
+    /// The size of every record (ciphertext plus tag), except the last one.
+    pub rs: u32,
+    /// The key identifier, used by the receiver to pick the input keying material.
+    pub keyid: Vec<u8>,
+}
+
+impl Header {
+    /// Parses the header at the start of an `aes128gcm` payload.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The encoded payload.
+    ///
+    /// # Returns
+    ///
+    /// The parsed header and the number of bytes it occupies, or
+    /// `Error::InvalidSegmentSize` if the record size is below 18.
+    pub fn parse(payload: &[u8]) -> Result<(Header, usize), Error> {
+        if payload.len() < FIXED_HEADER_LEN {
+            return Err(Error::TruncatedInput);
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&payload[..SALT_LEN]);
+        let mut rs = [0u8; 4];
+        rs.copy_from_slice(&payload[SALT_LEN..SALT_LEN + 4]);
+        let rs = u32::from_be_bytes(rs);
+        if rs < MIN_RECORD_SIZE {
+            return Err(Error::InvalidSegmentSize);
+        }
+
+        let idlen = payload[SALT_LEN + 4] as usize;
+        let header_len = FIXED_HEADER_LEN + idlen;
+        let keyid = payload
+            .get(FIXED_HEADER_LEN..header_len)
+            .ok_or(Error::TruncatedInput)?
+            .to_vec();
+
+        Ok((Header { salt, rs, keyid }, header_len))
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let idlen = u8::try_from(self.keyid.len()).map_err(|_| Error::InvalidKeyId)?;
+        out.extend_from_slice(&self.salt);
+        out.extend_from_slice(&self.rs.to_be_bytes());
+        out.push(idlen);
+        out.extend_from_slice(&self.keyid);
+        Ok(())
+    }
+}
+
+/// Encrypts the given plaintext as an `aes128gcm` payload with a random salt.
+///
+/// # Arguments
+///
+/// * `ikm` - The input keying material shared with the receiver.
+/// * `keyid` - The key identifier to store in the header (at most 255 bytes).
+/// * `rs` - The record size; must be at least 18.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The encoded payload, header included.
+pub fn encrypt(ikm: &[u8], keyid: &[u8], rs: u32, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    rng::fill(&mut salt)?;
+    encrypt_with_salt(ikm, &salt, keyid, rs, plaintext)
+}
+
+/// Encrypts the given plaintext as an `aes128gcm` payload with a caller-supplied salt.
+///
+/// The salt must never be reused with the same input keying material; prefer
+/// [`encrypt`] unless the salt is negotiated out of band.
+///
+/// # Arguments
+///
+/// * `ikm` - The input keying material shared with the receiver.
+/// * `salt` - The 16-byte salt to store in the header.
+/// * `keyid` - The key identifier to store in the header (at most 255 bytes).
+/// * `rs` - The record size; must be at least 18.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The encoded payload, header included.
+pub fn encrypt_with_salt(
+    ikm: &[u8],
+    salt: &[u8; SALT_LEN],
+    keyid: &[u8],
+    rs: u32,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    if rs < MIN_RECORD_SIZE {
+        return Err(Error::InvalidSegmentSize);
+    }
+    let header = Header {
+        salt: *salt,
+        rs,
+        keyid: keyid.to_vec(),
+    };
+    let (key, nonces) = derive(ikm, salt);
+
+    // Each record carries at most `rs - 17` bytes of plaintext.
+    let chunk_len = rs as usize - TAG_LEN - 1;
+    let records = plaintext.len() / chunk_len + 1;
+
+    let mut out = Vec::with_capacity(
+        FIXED_HEADER_LEN + keyid.len() + plaintext.len() + records * (TAG_LEN + 1),
+    );
+    header.encode(&mut out)?;
+
+    let mut chunks = plaintext.chunks(chunk_len).peekable();
+    let mut seq = 0u64;
+    loop {
+        let chunk = chunks.next().unwrap_or(&[]);
+        // A plaintext that exactly fills its last record still needs a final,
+        // empty record so that the last delimiter can be written.
+        let last = chunks.peek().is_none() && chunk.len() < chunk_len;
+
+        let mut record = Vec::with_capacity(chunk.len() + 1 + TAG_LEN);
+        record.extend_from_slice(chunk);
+        record.push(if last { LAST_DELIMITER } else { DELIMITER });
+        let nonce = Nonce::assume_unique_for_key(nonces.nonce_for(seq));
+        key.seal_in_place_append_tag(nonce, Aad::empty(), &mut record)
+            .map_err(|_| Error::EncryptionFailed)?;
+        out.extend_from_slice(&record);
+
+        if last {
+            return Ok(out);
+        }
+        seq += 1;
+    }
+}
+
+/// Decrypts an `aes128gcm` payload.
+///
+/// Use [`Header::parse`] first when the input keying material has to be
+/// selected by key identifier.
+///
+/// # Arguments
+///
+/// * `ikm` - The input keying material shared with the sender.
+/// * `payload` - The encoded payload, header included.
+///
+/// # Returns
+///
+/// The decrypted plaintext, with record padding removed. Fails with
+/// `Error::TruncatedInput` if records are missing from the end, and with
+/// `Error::DecryptionFailed` if a record does not authenticate.
+pub fn decrypt(ikm: &[u8], payload: &[u8]) -> Result<Vec<u8>, Error> {
+    let (header, header_len) = Header::parse(payload)?;
+    let (key, nonces) = derive(ikm, &header.salt);
+
+    let body = &payload[header_len..];
+    if body.is_empty() {
+        return Err(Error::TruncatedInput);
+    }
+
+    let mut plaintext = Vec::with_capacity(body.len());
+    let mut records = body.chunks(header.rs as usize).peekable();
+    let mut seq = 0u64;
+    while let Some(record) = records.next() {
+        let last = records.peek().is_none();
+
+        let mut in_out = record.to_vec();
+        let nonce = Nonce::assume_unique_for_key(nonces.nonce_for(seq));
+        let opened = key
+            .open_in_place(nonce, Aad::empty(), &mut in_out)
+            .map_err(|_| Error::DecryptionFailed)?;
+
+        // Strip the zero padding, then check the delimiter matches the
+        // record's position so that truncated payloads are rejected.
+        let end = opened
+            .iter()
+            .rposition(|&b| b != 0)
+            .ok_or(Error::InvalidEncoding)?;
+        match (opened[end], last) {
+            (DELIMITER, false) | (LAST_DELIMITER, true) => {}
+            (DELIMITER, true) => return Err(Error::TruncatedInput),
+            _ => return Err(Error::InvalidEncoding),
+        }
+        plaintext.extend_from_slice(&opened[..end]);
+        seq += 1;
+    }
+
+    Ok(plaintext)
+}
+
+fn derive(ikm: &[u8], salt: &[u8; SALT_LEN]) -> (LessSafeKey, XorNonce) {
+    let prk = Salt::new(HKDF_SHA256, salt).extract(ikm);
+
+    // Both outputs are far below the HKDF-SHA256 limit of 8160 bytes.
+    let cek: UnboundKey = prk
+        .expand(&[CEK_INFO], &AES_128_GCM)
+        .expect("key length within HKDF bounds")
+        .into();
+
+    let mut nonce = [0u8; NONCE_LEN];
+    prk.expand(&[NONCE_INFO], OkmLen(NONCE_LEN))
+        .and_then(|okm| okm.fill(&mut nonce))
+        .expect("nonce length within HKDF bounds");
+
+    (LessSafeKey::new(cek), XorNonce::new(nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    #[test]
+    fn test_rfc8188_single_record() {
+        // RFC 8188, section 3.1.
+        let ikm = decode_hex("caa76567eb587a67e88129afed6b393d").unwrap();
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&decode_hex("23506cc6d16db65bf7bbf3a8f78c679b").unwrap());
+        let expected = decode_hex(
+            "23506cc6d16db65bf7bbf3a8f78c679b0000100000f8d015b9bdaa160044b902916a9a19\
+             bbe231908bdadcc101d4f0fe972f138638",
+        )
+        .unwrap();
+
+        let payload = encrypt_with_salt(&ikm, &salt, b"", 4096, b"I am the walrus")
+            .expect("encryption failed");
+        assert_eq!(payload, expected);
+        assert_eq!(
+            decrypt(&ikm, &payload).expect("decryption failed"),
+            b"I am the walrus"
+        );
+    }
+
+    #[test]
+    fn test_rfc8188_multiple_records_with_padding() {
+        // RFC 8188, section 3.2.
+        let ikm = decode_hex("04edd954fc549672ce45b5463296d3d5").unwrap();
+        let payload = decode_hex(
+            "b8d0a45a2358cca4e704df638b7faa5800000019026131ce1bc721cff827be03aa746628\
+             bf1ca3baa4722458c40f2a05d45be48fa8503dd3c7239d4e114284a60cf74ac2d622a4bfb8",
+        )
+        .unwrap();
+
+        let (header, _) = Header::parse(&payload).expect("header parse failed");
+        assert_eq!(header.rs, 25);
+        assert_eq!(header.keyid, b"a1");
+        assert_eq!(
+            decrypt(&ikm, &payload).expect("decryption failed"),
+            b"I am the walrus"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_and_truncation() {
+        let ikm = b"an example very very secret key.";
+        let plaintext = vec![7u8; 100];
+
+        // 33 bytes of plaintext per record: 100 bytes fill three records
+        // and a fourth carries the remaining byte.
+        let payload = encrypt(ikm, b"k1", 50, &plaintext).expect("encryption failed");
+        assert_eq!(
+            decrypt(ikm, &payload).expect("decryption failed"),
+            plaintext
+        );
+
+        let (_, header_len) = Header::parse(&payload).unwrap();
+        let truncated = &payload[..header_len + 50];
+        assert_eq!(decrypt(ikm, truncated), Err(Error::TruncatedInput));
+        assert_eq!(
+            decrypt(b"another example very secret key.", &payload),
+            Err(Error::DecryptionFailed)
+        );
+        assert_eq!(
+            encrypt(ikm, b"", 17, &plaintext),
+            Err(Error::InvalidSegmentSize)
+        );
+
+        let exact = encrypt(ikm, b"", 50, &plaintext[..66]).expect("encryption failed");
+        assert_eq!(
+            decrypt(ikm, &exact).expect("decryption failed"),
+            &plaintext[..66]
+        );
+    }
+}
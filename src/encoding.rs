@@ -0,0 +1,84 @@
+//! Text encodings for ciphertexts stored in JSON, URLs or environment
+//! variables.
+
+use alloc::{format, string::String, vec::Vec};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+use crate::error::Error;
+
+/// The base64 alphabet used for text output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Base64Alphabet {
+    /// RFC 4648 section 4: `+` and `/`, with `=` padding.
+    #[default]
+    Standard,
+    /// RFC 4648 section 5: `-` and `_`, without padding. Safe in URLs, file
+    /// names and environment variables.
+    UrlSafe,
+}
+
+pub(crate) fn encode_base64(bytes: &[u8], alphabet: Base64Alphabet) -> String {
+    match alphabet {
+        Base64Alphabet::Standard => STANDARD.encode(bytes),
+        Base64Alphabet::UrlSafe => URL_SAFE_NO_PAD.encode(bytes),
+    }
+}
+
+pub(crate) fn decode_base64(text: &str, alphabet: Base64Alphabet) -> Result<Vec<u8>, Error> {
+    match alphabet {
+        Base64Alphabet::Standard => STANDARD.decode(text),
+        Base64Alphabet::UrlSafe => URL_SAFE_NO_PAD.decode(text),
+    }
+    .map_err(|_| Error::InvalidEncoding)
+}
+
+/// Encodes bytes as lowercase hex.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decodes hex in either case.
+pub(crate) fn decode_hex(text: &str) -> Result<Vec<u8>, Error> {
+    if !text.len().is_multiple_of(2) {
+        return Err(Error::InvalidEncoding);
+    }
+    let digit = |c: u8| {
+        (c as char)
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or(Error::InvalidEncoding)
+    };
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings() {
+        let bytes = [0xfb, 0xff, 0x00, 0x10];
+        assert_eq!(encode_base64(&bytes, Base64Alphabet::Standard), "+/8AEA==");
+        assert_eq!(encode_base64(&bytes, Base64Alphabet::UrlSafe), "-_8AEA");
+        assert_eq!(encode_hex(&bytes), "fbff0010");
+        assert_eq!(decode_hex("FBff0010").unwrap(), bytes);
+
+        // Each alphabet only accepts its own output.
+        assert_eq!(
+            decode_base64("-_8AEA", Base64Alphabet::Standard),
+            Err(Error::InvalidEncoding)
+        );
+        assert_eq!(
+            decode_base64("+/8AEA==", Base64Alphabet::UrlSafe),
+            Err(Error::InvalidEncoding)
+        );
+        for bad in ["f", "+f", "0g", "é0", "éé"] {
+            assert_eq!(decode_hex(bad), Err(Error::InvalidEncoding), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,1306 @@
+//! Self-describing ciphertext envelopes.
+//!
+//! An envelope carries everything needed to decrypt it except the key:
+//!
+//! ```text
+//! offset  size  field
+//! 0       1     version (currently 2)
+//! 1       1     algorithm identifier, see `Algorithm::id`
+//! 2       1     flags, one bit per optional extension
+//! 3       ..    extensions, in flag bit order
+//! ..      12    nonce
+//! ..      16    authentication tag
+//! ..      ..    ciphertext
+//! ```
+//!
+//! Extensions:
+//!
+//! * bit 0, per-message subkey: a 32-byte HKDF salt followed by a 32-byte key
+//!   commitment, see [`seal_with_subkey`].
+//! * bit 1, key id: a length byte followed by the id of the key the envelope
+//!   was sealed with, see [`Keyring`](crate::keyring::Keyring).
+//! * bit 2, cascade: the 12-byte nonce of an inner ChaCha20-Poly1305 layer
+//!   under the AES-256-GCM payload, see [`seal_cascade`]. Only valid with the
+//!   AES-256-GCM algorithm identifier.
+//!
+//! Everything before the nonce is authenticated as associated data, so a blob
+//! cannot be re-labelled without failing decryption. Version 1 envelopes,
+//! which have neither the flags byte nor extensions, are still accepted.
+//!
+//! # Canonical header bytes
+//!
+//! The associated data is the header's canonical encoding, returned by
+//! [`Header::canonical_bytes`]. Other implementations must produce exactly
+//! these bytes from the header fields:
+//!
+//! 1. The version byte, then the algorithm identifier.
+//! 2. For version 1, nothing else.
+//! 3. For version 2, the flags byte: bit 0 set if and only if a subkey is
+//!    present, bit 1 if and only if a key id is present, and bit 2 if and
+//!    only if the envelope is a cascade. Bits 3 to 7 are never set.
+//! 4. If a subkey is present, its 32-byte salt, then its 32-byte commitment.
+//! 5. If a key id is present, its length as one byte (1 to 255), then the id.
+//! 6. If the envelope is a cascade, the 12-byte inner nonce.
+//!
+//! There are no optional encodings: a header that parses re-encodes to the
+//! same bytes it was read from, and unknown flags are rejected rather than
+//! carried along.
+//!
+//! # Wrapped data keys
+//!
+//! [`seal_wrapped`] encrypts each payload under a fresh data-encryption key
+//! (DEK) and stores that key, encrypted under a long-lived key-encryption key
+//! (KEK), in front of it:
+//!
+//! ```text
+//! offset  size  field
+//! 0       1     length of the wrapped DEK
+//! 1       ..    wrapped DEK: an AES-256-GCM envelope of the DEK under the KEK
+//! ..      ..    payload: an AES-256-GCM envelope under the DEK
+//! ```
+//!
+//! Only the wrapped DEK depends on the KEK, so a KEK can be rotated by
+//! re-wrapping a few dozen bytes per object instead of re-encrypting the
+//! payloads.
+
+use alloc::{string::String, string::ToString, vec::Vec};
+use core::fmt;
+
+use ring::constant_time;
+use ring::rand::SystemRandom;
+use zeroize::Zeroizing;
+
+use crate::aead::{Algorithm, Cipher, NONCE_LEN, TAG_LEN};
+use crate::encoding::{self, Base64Alphabet};
+use crate::error::Error;
+use crate::kdf::{self, COMMITMENT_LEN, SUBKEY_SALT_LEN};
+use crate::key::Key;
+use crate::keyring::{KeyId, MAX_KEY_ID_LEN};
+use crate::nonce::NonceStrategy;
+use crate::rng;
+
+/// The envelope format version written by this crate.
+pub const VERSION: u8 = 2;
+
+/// Length in bytes of the header of an envelope without extensions.
+pub const MIN_HEADER_LEN: usize = 3 + NONCE_LEN + TAG_LEN;
+
+const FLAG_SUBKEY: u8 = 0x01;
+const FLAG_KEY_ID: u8 = 0x02;
+const FLAG_CASCADE: u8 = 0x04;
+const KNOWN_FLAGS: u8 = FLAG_SUBKEY | FLAG_KEY_ID | FLAG_CASCADE;
+
+// Version, algorithm and flags, followed by every extension.
+const MAX_PREFIX_LEN: usize = 3 + SUBKEY_SALT_LEN + COMMITMENT_LEN + 1 + MAX_KEY_ID_LEN + NONCE_LEN;
+
+/// The salt and key commitment of an envelope sealed under a per-message subkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subkey {
+    salt: [u8; SUBKEY_SALT_LEN],
+    commitment: [u8; COMMITMENT_LEN],
+}
+
+impl Subkey {
+    /// Returns the HKDF salt the subkey was derived with.
+    pub fn salt(&self) -> &[u8; SUBKEY_SALT_LEN] {
+        &self.salt
+    }
+}
+
+/// The header at the start of every envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    version: u8,
+    algorithm: Algorithm,
+    subkey: Option<Subkey>,
+    key_id: Option<KeyId>,
+    /// The nonce of the inner layer of a cascade envelope.
+    cascade: Option<[u8; NONCE_LEN]>,
+    nonce: [u8; NONCE_LEN],
+    tag: [u8; TAG_LEN],
+}
+
+/// The authenticated part of a header, kept on the stack.
+struct Prefix {
+    bytes: [u8; MAX_PREFIX_LEN],
+    len: usize,
+}
+
+impl Prefix {
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl Header {
+    /// A header for a new envelope; the nonce and tag are filled in by sealing.
+    fn new(algorithm: Algorithm, subkey: Option<Subkey>, key_id: Option<KeyId>) -> Header {
+        Header {
+            version: VERSION,
+            algorithm,
+            subkey,
+            key_id,
+            cascade: None,
+            nonce: [0u8; NONCE_LEN],
+            tag: [0u8; TAG_LEN],
+        }
+    }
+
+    /// Returns the envelope format version.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the algorithm the payload was encrypted with.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Returns the subkey derivation parameters, if the envelope was sealed
+    /// with [`seal_with_subkey`].
+    pub fn subkey(&self) -> Option<&Subkey> {
+        self.subkey.as_ref()
+    }
+
+    /// Returns the id of the key the envelope was sealed with, if recorded.
+    pub fn key_id(&self) -> Option<&KeyId> {
+        self.key_id.as_ref()
+    }
+
+    /// Returns whether the payload is a cascade, see [`seal_cascade`].
+    pub fn is_cascade(&self) -> bool {
+        self.cascade.is_some()
+    }
+
+    /// Returns the nonce the payload was encrypted with.
+    pub fn nonce(&self) -> &[u8; NONCE_LEN] {
+        &self.nonce
+    }
+
+    /// Returns the authentication tag of the payload.
+    pub fn tag(&self) -> &[u8; TAG_LEN] {
+        &self.tag
+    }
+
+    /// Returns the canonical encoding of the header fields, without the
+    /// nonce and tag. This is the associated data of the payload; see the
+    /// [module documentation](self) for the exact rules.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        self.prefix().as_slice().to_vec()
+    }
+
+    /// Returns the encoded length of the header in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.prefix().len + NONCE_LEN + TAG_LEN
+    }
+
+    /// Parses the header at the start of an encoded envelope.
+    fn parse(bytes: &[u8]) -> Result<Header, Error> {
+        let version = *bytes.first().ok_or(Error::TruncatedInput)?;
+        let (flags, mut offset) = match version {
+            1 => (0, 2),
+            VERSION => (*bytes.get(2).ok_or(Error::TruncatedInput)?, 3),
+            _ => return Err(Error::UnsupportedVersion),
+        };
+        let algorithm = Algorithm::from_id(*bytes.get(1).ok_or(Error::TruncatedInput)?)
+            .ok_or(Error::UnsupportedAlgorithm)?;
+        // An unknown extension cannot be skipped safely: it may change how
+        // the payload has to be decrypted.
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::UnsupportedVersion);
+        }
+
+        let mut take = |len: usize| -> Result<&[u8], Error> {
+            let field = bytes
+                .get(offset..offset + len)
+                .ok_or(Error::TruncatedInput)?;
+            offset += len;
+            Ok(field)
+        };
+
+        let subkey = if flags & FLAG_SUBKEY != 0 {
+            Some(Subkey {
+                salt: take(SUBKEY_SALT_LEN)?.try_into().unwrap(),
+                commitment: take(COMMITMENT_LEN)?.try_into().unwrap(),
+            })
+        } else {
+            None
+        };
+        let key_id = if flags & FLAG_KEY_ID != 0 {
+            let len = take(1)?[0] as usize;
+            Some(KeyId::new(take(len)?)?)
+        } else {
+            None
+        };
+        let cascade = if flags & FLAG_CASCADE != 0 {
+            if algorithm != Algorithm::Aes256Gcm {
+                return Err(Error::UnsupportedAlgorithm);
+            }
+            Some(take(NONCE_LEN)?.try_into().unwrap())
+        } else {
+            None
+        };
+        let nonce = take(NONCE_LEN)?.try_into().unwrap();
+        let tag = take(TAG_LEN)?.try_into().unwrap();
+
+        Ok(Header {
+            version,
+            algorithm,
+            subkey,
+            key_id,
+            cascade,
+            nonce,
+            tag,
+        })
+    }
+
+    /// The header fields bound to the ciphertext as associated data.
+    fn prefix(&self) -> Prefix {
+        let mut prefix = Prefix {
+            bytes: [0u8; MAX_PREFIX_LEN],
+            len: 0,
+        };
+        prefix.push(&[self.version, self.algorithm.id()]);
+        if self.version == 1 {
+            return prefix;
+        }
+
+        let mut flags = 0;
+        if self.subkey.is_some() {
+            flags |= FLAG_SUBKEY;
+        }
+        if self.key_id.is_some() {
+            flags |= FLAG_KEY_ID;
+        }
+        if self.cascade.is_some() {
+            flags |= FLAG_CASCADE;
+        }
+        prefix.push(&[flags]);
+        if let Some(subkey) = &self.subkey {
+            prefix.push(&subkey.salt);
+            prefix.push(&subkey.commitment);
+        }
+        if let Some(key_id) = &self.key_id {
+            prefix.push(&[key_id.as_bytes().len() as u8]);
+            prefix.push(key_id.as_bytes());
+        }
+        if let Some(inner_nonce) = &self.cascade {
+            prefix.push(inner_nonce);
+        }
+        prefix
+    }
+
+    /// Builds the ciphers for this envelope, deriving and checking the
+    /// per-message subkey when there is one. The second cipher is the inner
+    /// layer of a cascade.
+    fn ciphers(&self, key: &Key) -> Result<(Cipher, Option<Cipher>), Error> {
+        let message_key = match &self.subkey {
+            None => None,
+            Some(subkey) => {
+                let (message_key, commitment) =
+                    kdf::derive_message_key(self.algorithm, key, &subkey.salt)?;
+                constant_time::verify_slices_are_equal(&commitment, &subkey.commitment)
+                    .map_err(|_| Error::DecryptionFailed)?;
+                Some(message_key)
+            }
+        };
+        let key = message_key.as_ref().unwrap_or(key);
+
+        if self.cascade.is_none() {
+            return Ok((Cipher::new(self.algorithm, key)?, None));
+        }
+        let (outer, inner) = kdf::derive_cascade_keys(key)?;
+        Ok((
+            Cipher::new(Algorithm::Aes256Gcm, &outer)?,
+            Some(Cipher::new(Algorithm::ChaCha20Poly1305, &inner)?),
+        ))
+    }
+
+    /// Decrypts the payload in place and returns the plaintext, which is the
+    /// start of `payload`.
+    fn open_payload<'a>(&self, key: &Key, payload: &'a mut [u8]) -> Result<&'a mut [u8], Error> {
+        let (cipher, inner) = self.ciphers(key)?;
+        let aad = self.prefix();
+        cipher.decrypt_in_place_detached(&self.nonce, aad.as_slice(), &self.tag, payload)?;
+        match (inner, &self.cascade) {
+            (Some(inner), Some(inner_nonce)) => {
+                inner.open_in_place(inner_nonce, payload, aad.as_slice())
+            }
+            _ => Ok(payload),
+        }
+    }
+}
+
+/// A ciphertext together with the header needed to decrypt it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    header: Header,
+    ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+    /// Returns the envelope header.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the encrypted payload, without the tag.
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Encodes the envelope as a single byte blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.header.encoded_len() + self.ciphertext.len());
+        out.extend_from_slice(self.header.prefix().as_slice());
+        out.extend_from_slice(&self.header.nonce);
+        out.extend_from_slice(&self.header.tag);
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    /// Encodes the envelope as base64 text in the given alphabet.
+    pub fn to_base64(&self, alphabet: Base64Alphabet) -> String {
+        encoding::encode_base64(&self.to_bytes(), alphabet)
+    }
+
+    /// Decodes an envelope from [`Envelope::to_base64`] output.
+    ///
+    /// # Returns
+    ///
+    /// The envelope, `Error::InvalidEncoding` if `text` is not base64 in the
+    /// given alphabet, or any error [`Envelope::from_bytes`] returns.
+    pub fn from_base64(text: &str, alphabet: Base64Alphabet) -> Result<Envelope, Error> {
+        Envelope::from_bytes(&encoding::decode_base64(text, alphabet)?)
+    }
+
+    /// Encodes the envelope as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        encoding::encode_hex(&self.to_bytes())
+    }
+
+    /// Decodes an envelope from hex in either case.
+    ///
+    /// # Returns
+    ///
+    /// The envelope, `Error::InvalidEncoding` if `text` is not hex, or any
+    /// error [`Envelope::from_bytes`] returns.
+    pub fn from_hex(text: &str) -> Result<Envelope, Error> {
+        Envelope::from_bytes(&encoding::decode_hex(text)?)
+    }
+
+    /// Decodes an envelope from a byte blob.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The encoded envelope.
+    ///
+    /// # Returns
+    ///
+    /// The envelope, `Error::TruncatedInput` if the blob is shorter than its
+    /// header, or `Error::UnsupportedVersion`/`Error::UnsupportedAlgorithm` if
+    /// the header names a format, extension or algorithm this crate does not
+    /// know.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Envelope, Error> {
+        let header = Header::parse(bytes)?;
+        Ok(Envelope {
+            ciphertext: bytes[header.encoded_len()..].to_vec(),
+            header,
+        })
+    }
+
+    /// Encrypts the given plaintext into an envelope.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher` - The cipher to encrypt with; its algorithm is recorded in the header.
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The envelope holding the ciphertext, nonce and tag.
+    pub fn seal(cipher: &Cipher, plaintext: &[u8]) -> Result<Envelope, Error> {
+        Self::seal_header(
+            Header::new(cipher.algorithm(), None, None),
+            cipher,
+            plaintext,
+        )
+    }
+
+    /// Encrypts the given plaintext into an envelope that records the id of
+    /// the key it was sealed with.
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher` - The cipher to encrypt with; its algorithm is recorded in the header.
+    /// * `key_id` - The id of the cipher's key, e.g. its name in a [`Keyring`](crate::keyring::Keyring).
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The envelope holding the key id, ciphertext, nonce and tag.
+    pub fn seal_with_key_id(
+        cipher: &Cipher,
+        key_id: &KeyId,
+        plaintext: &[u8],
+    ) -> Result<Envelope, Error> {
+        let header = Header::new(cipher.algorithm(), None, Some(*key_id));
+        Self::seal_header(header, cipher, plaintext)
+    }
+
+    /// Encrypts the given plaintext into an envelope under a fresh subkey.
+    ///
+    /// A random salt is drawn for every message and the encryption key is
+    /// derived from `master` and the salt with HKDF-SHA256. The salt and a
+    /// commitment to the derived key are stored in the header; on decryption
+    /// the commitment is checked first, so the envelope only opens under the
+    /// master key it was sealed with.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `master` - The master key; its length must match the algorithm.
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The envelope holding the ciphertext, derivation salt, nonce and tag.
+    pub fn seal_with_subkey(
+        algorithm: Algorithm,
+        master: &Key,
+        plaintext: &[u8],
+    ) -> Result<Envelope, Error> {
+        algorithm.check_key_len(master.as_bytes().len())?;
+
+        let mut salt = [0u8; SUBKEY_SALT_LEN];
+        rng::fill(&mut salt)?;
+        let (message_key, commitment) = kdf::derive_message_key(algorithm, master, &salt)?;
+
+        let cipher = Cipher::new(algorithm, &message_key)?;
+        let header = Header::new(algorithm, Some(Subkey { salt, commitment }), None);
+        Self::seal_header(header, &cipher, plaintext)
+    }
+
+    /// Encrypts the given plaintext twice, with AES-256-GCM over
+    /// ChaCha20-Poly1305, for policies that require two independent cipher
+    /// families.
+    ///
+    /// Both layer keys are derived from `master` with HKDF-SHA256 under
+    /// distinct labels, and each layer has its own random nonce. Both layers
+    /// authenticate the header.
+    ///
+    /// # Arguments
+    ///
+    /// * `master` - A 256-bit master key.
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The envelope, whose payload is [`TAG_LEN`] bytes longer than for a
+    /// single layer.
+    pub fn seal_cascade(master: &Key, plaintext: &[u8]) -> Result<Envelope, Error> {
+        let (outer, inner) = kdf::derive_cascade_keys(master)?;
+
+        let mut header = Header::new(Algorithm::Aes256Gcm, None, None);
+        let mut inner_nonce = [0u8; NONCE_LEN];
+        rng::fill(&mut inner_nonce)?;
+        header.cascade = Some(inner_nonce);
+
+        let inner = Cipher::new(Algorithm::ChaCha20Poly1305, &inner)?;
+        let sealed =
+            inner.encrypt_with_nonce(inner_nonce, plaintext, header.prefix().as_slice())?;
+        Self::seal_header(header, &Cipher::new(Algorithm::Aes256Gcm, &outer)?, &sealed)
+    }
+
+    /// Encrypts the given plaintext into an envelope, drawing the nonce from
+    /// the given strategy instead of the system random number generator.
+    ///
+    /// This is how to seal on targets without an operating system RNG: pass
+    /// an [`RngNonce`](crate::nonce::RngNonce) over the platform's own
+    /// generator, or a [`NonceSequence`](crate::nonce::NonceSequence).
+    ///
+    /// # Arguments
+    ///
+    /// * `cipher` - The cipher to encrypt with; its algorithm is recorded in the header.
+    /// * `nonces` - The source of the nonce.
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The envelope holding the ciphertext, nonce and tag, or the error the
+    /// strategy returns.
+    pub fn seal_with_strategy<S: NonceStrategy + ?Sized>(
+        cipher: &Cipher,
+        nonces: &mut S,
+        plaintext: &[u8],
+    ) -> Result<Envelope, Error> {
+        let mut header = Header::new(cipher.algorithm(), None, None);
+        header.nonce = nonces.next_nonce()?;
+        Self::seal_under_nonce(header, cipher, plaintext)
+    }
+
+    fn seal_header(
+        mut header: Header,
+        cipher: &Cipher,
+        plaintext: &[u8],
+    ) -> Result<Envelope, Error> {
+        rng::fill(&mut header.nonce)?;
+        Self::seal_under_nonce(header, cipher, plaintext)
+    }
+
+    fn seal_under_nonce(
+        mut header: Header,
+        cipher: &Cipher,
+        plaintext: &[u8],
+    ) -> Result<Envelope, Error> {
+        let mut ciphertext = plaintext.to_vec();
+        header.tag = cipher.encrypt_in_place_detached(
+            &header.nonce,
+            header.prefix().as_slice(),
+            &mut ciphertext,
+        )?;
+
+        Ok(Envelope { header, ciphertext })
+    }
+
+    /// Decrypts the envelope.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the envelope was sealed with.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext.
+    pub fn open(&self, key: &Key) -> Result<Vec<u8>, Error> {
+        let mut in_out = self.ciphertext.clone();
+        let plaintext_len = self.header.open_payload(key, &mut in_out)?.len();
+        in_out.truncate(plaintext_len);
+        Ok(in_out)
+    }
+}
+
+/// Encrypts the given plaintext into a self-describing envelope blob.
+///
+/// # Arguments
+///
+/// * `algorithm` - The AEAD algorithm to use.
+/// * `key` - The key; its length must match the algorithm.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The encoded envelope, which [`open`] decrypts with only the key.
+pub fn seal(algorithm: Algorithm, key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let cipher = Cipher::new(algorithm, key)?;
+    Ok(Envelope::seal(&cipher, plaintext)?.to_bytes())
+}
+
+/// Encrypts the given plaintext into an envelope blob under a fresh,
+/// key-committing subkey derived from `master`.
+///
+/// See [`Envelope::seal_with_subkey`]. [`open`] decrypts the blob with the
+/// master key.
+pub fn seal_with_subkey(
+    algorithm: Algorithm,
+    master: &Key,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    Ok(Envelope::seal_with_subkey(algorithm, master, plaintext)?.to_bytes())
+}
+
+/// Encrypts the given plaintext into a cascade envelope blob, see
+/// [`Envelope::seal_cascade`]. [`open`] decrypts it with `master`.
+pub fn seal_cascade(master: &Key, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    Ok(Envelope::seal_cascade(master, plaintext)?.to_bytes())
+}
+
+/// Decrypts a blob produced by [`seal`], [`seal_with_subkey`] or [`seal_cascade`].
+///
+/// # Arguments
+///
+/// * `key` - The key the blob was sealed with.
+/// * `blob` - The encoded envelope.
+///
+/// # Returns
+///
+/// The decrypted plaintext.
+pub fn open(key: &Key, blob: &[u8]) -> Result<Vec<u8>, Error> {
+    Envelope::from_bytes(blob)?.open(key)
+}
+
+/// Encrypts the given plaintext under a fresh data-encryption key and wraps
+/// that key under `kek`, see [Wrapped data keys](self#wrapped-data-keys).
+///
+/// # Arguments
+///
+/// * `kek` - A 256-bit key-encryption key.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The wrapped key and payload in one blob, which [`open_wrapped`] decrypts
+/// with only the KEK.
+pub fn seal_wrapped(kek: &Key, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let dek = Key::generate(&SystemRandom::new())?;
+    let wrapped = seal(Algorithm::Aes256Gcm, kek, dek.as_bytes())?;
+    let payload = seal(Algorithm::Aes256Gcm, &dek, plaintext)?;
+
+    let mut blob = Vec::with_capacity(1 + wrapped.len() + payload.len());
+    blob.push(wrapped.len() as u8);
+    blob.extend_from_slice(&wrapped);
+    blob.extend_from_slice(&payload);
+    Ok(blob)
+}
+
+/// Unwraps the data-encryption key of a blob produced by [`seal_wrapped`]
+/// and decrypts the payload with it.
+///
+/// # Arguments
+///
+/// * `kek` - The key-encryption key the blob was sealed with.
+/// * `blob` - The wrapped key and payload.
+///
+/// # Returns
+///
+/// The decrypted plaintext, or `Error::TruncatedInput` if the blob is too
+/// short for the wrapped key it announces.
+pub fn open_wrapped(kek: &Key, blob: &[u8]) -> Result<Vec<u8>, Error> {
+    let (&wrapped_len, rest) = blob.split_first().ok_or(Error::TruncatedInput)?;
+    if rest.len() < wrapped_len as usize {
+        return Err(Error::TruncatedInput);
+    }
+    let (wrapped, payload) = rest.split_at(wrapped_len as usize);
+    let dek = Key::from_bytes(&Zeroizing::new(open(kek, wrapped)?))?;
+    open(&dek, payload)
+}
+
+/// Like [`seal`], but returns the envelope as base64 text.
+///
+/// # Arguments
+///
+/// * `algorithm` - The AEAD algorithm to use.
+/// * `key` - The key; its length must match the algorithm.
+/// * `plaintext` - The data to encrypt.
+/// * `alphabet` - The base64 alphabet to encode with.
+pub fn seal_to_base64(
+    algorithm: Algorithm,
+    key: &Key,
+    plaintext: &[u8],
+    alphabet: Base64Alphabet,
+) -> Result<String, Error> {
+    Ok(encoding::encode_base64(
+        &seal(algorithm, key, plaintext)?,
+        alphabet,
+    ))
+}
+
+/// Decrypts base64 text produced by [`seal_to_base64`].
+///
+/// # Returns
+///
+/// The decrypted plaintext, or `Error::InvalidEncoding` if `text` is not
+/// base64 in the given alphabet.
+pub fn open_from_base64(key: &Key, text: &str, alphabet: Base64Alphabet) -> Result<Vec<u8>, Error> {
+    open(key, &encoding::decode_base64(text, alphabet)?)
+}
+
+/// Like [`seal`], but returns the envelope as lowercase hex.
+pub fn seal_to_hex(algorithm: Algorithm, key: &Key, plaintext: &[u8]) -> Result<String, Error> {
+    Ok(encoding::encode_hex(&seal(algorithm, key, plaintext)?))
+}
+
+/// Decrypts hex text produced by [`seal_to_hex`].
+///
+/// # Returns
+///
+/// The decrypted plaintext, or `Error::InvalidEncoding` if `text` is not hex.
+pub fn open_from_hex(key: &Key, text: &str) -> Result<Vec<u8>, Error> {
+    open(key, &encoding::decode_hex(text)?)
+}
+
+/// Decrypts an envelope whose plaintext must be exactly `N` bytes long.
+///
+/// The plaintext is decrypted into a stack buffer. No heap allocation takes
+/// place unless the envelope has a subkey or cascade extension, whose derived
+/// keys are heap-backed. This suits fixed-layout records on realtime paths.
+///
+/// # Arguments
+///
+/// * `key` - The key the envelope was sealed with.
+/// * `envelope` - The envelope to decrypt.
+///
+/// # Returns
+///
+/// The decrypted plaintext, or `Error::InvalidPlaintextLength` if the
+/// envelope does not hold exactly `N` bytes.
+pub fn decrypt_fixed<const N: usize>(key: &Key, envelope: &Envelope) -> Result<[u8; N], Error> {
+    open_fixed_parts(key, &envelope.header, &envelope.ciphertext)
+}
+
+/// Decrypts an encoded envelope whose plaintext must be exactly `N` bytes long.
+///
+/// Like [`decrypt_fixed`], but reads the blob in place instead of going
+/// through [`Envelope::from_bytes`], which copies the ciphertext.
+///
+/// # Arguments
+///
+/// * `key` - The key the envelope was sealed with.
+/// * `blob` - The encoded envelope.
+///
+/// # Returns
+///
+/// The decrypted plaintext, or `Error::InvalidPlaintextLength` if the
+/// envelope does not hold exactly `N` bytes.
+pub fn open_fixed<const N: usize>(key: &Key, blob: &[u8]) -> Result<[u8; N], Error> {
+    let header = Header::parse(blob)?;
+    open_fixed_parts(key, &header, &blob[header.encoded_len()..])
+}
+
+fn open_fixed_parts<const N: usize>(
+    key: &Key,
+    header: &Header,
+    ciphertext: &[u8],
+) -> Result<[u8; N], Error> {
+    if header.is_cascade() {
+        // The inner tag makes the payload longer than `N`.
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = header.open_payload(key, &mut in_out)?;
+        return <[u8; N]>::try_from(&*plaintext).map_err(|_| Error::InvalidPlaintextLength {
+            expected: N,
+            actual: plaintext.len(),
+        });
+    }
+
+    let mut in_out: [u8; N] = ciphertext
+        .try_into()
+        .map_err(|_| Error::InvalidPlaintextLength {
+            expected: N,
+            actual: ciphertext.len(),
+        })?;
+    header.open_payload(key, &mut in_out)?;
+    Ok(in_out)
+}
+
+/// Encrypts a buffer in place into a self-describing envelope.
+///
+/// On return `buf` holds the encoded envelope, byte for byte what [`seal`]
+/// produces. The plaintext is shifted back to make room for the header and
+/// encrypted where it lies, so no heap allocation takes place when `buf` has
+/// [`MIN_HEADER_LEN`] bytes of spare capacity.
+///
+/// # Arguments
+///
+/// * `cipher` - The cipher to encrypt with; its algorithm is recorded in the header.
+/// * `buf` - The plaintext on input, the encoded envelope on output.
+pub fn seal_in_place(cipher: &Cipher, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let plaintext_len = buf.len();
+    buf.resize(MIN_HEADER_LEN + plaintext_len, 0);
+    buf.copy_within(..plaintext_len, MIN_HEADER_LEN);
+    seal_behind_header(cipher, buf)
+}
+
+/// Encrypts a plaintext given as several fragments into an envelope blob,
+/// without concatenating them first.
+///
+/// Each fragment is copied once, straight into the envelope; the result is
+/// the same as [`seal`] of the concatenated fragments.
+///
+/// # Arguments
+///
+/// * `cipher` - The cipher to encrypt with; its algorithm is recorded in the header.
+/// * `parts` - The fragments of the data to encrypt, in order.
+///
+/// # Returns
+///
+/// The encoded envelope, which [`open`] decrypts to the concatenation.
+pub fn seal_vectored(cipher: &Cipher, parts: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let len = parts.iter().map(|part| part.len()).sum::<usize>();
+    let mut buf = Vec::with_capacity(MIN_HEADER_LEN + len);
+    buf.resize(MIN_HEADER_LEN, 0);
+    for part in parts {
+        buf.extend_from_slice(part);
+    }
+    seal_behind_header(cipher, &mut buf)?;
+    Ok(buf)
+}
+
+/// Encrypts the plaintext behind the first [`MIN_HEADER_LEN`] bytes of `buf`
+/// and writes a header without extensions into them.
+fn seal_behind_header(cipher: &Cipher, buf: &mut [u8]) -> Result<(), Error> {
+    let mut header = Header::new(cipher.algorithm(), None, None);
+    rng::fill(&mut header.nonce)?;
+    debug_assert_eq!(header.encoded_len(), MIN_HEADER_LEN);
+
+    let prefix = header.prefix();
+    let (head, payload) = buf.split_at_mut(MIN_HEADER_LEN);
+    header.tag = cipher.encrypt_in_place_detached(&header.nonce, prefix.as_slice(), payload)?;
+
+    let (aad, head) = head.split_at_mut(prefix.len);
+    aad.copy_from_slice(prefix.as_slice());
+    head[..NONCE_LEN].copy_from_slice(&header.nonce);
+    head[NONCE_LEN..].copy_from_slice(&header.tag);
+    Ok(())
+}
+
+/// Decrypts an encoded envelope in place.
+///
+/// Envelopes without a subkey or cascade extension are opened without any
+/// heap allocation. For the two extensions, the derived keys are still held
+/// in heap-backed [`Key`]s.
+///
+/// # Arguments
+///
+/// * `key` - The key the envelope was sealed with.
+/// * `blob` - The encoded envelope; its payload is overwritten with the
+///   plaintext.
+///
+/// # Returns
+///
+/// The plaintext, which is the tail of `blob` after the header.
+pub fn open_in_place<'a>(key: &Key, blob: &'a mut [u8]) -> Result<&'a mut [u8], Error> {
+    let header = Header::parse(blob)?;
+    header.open_payload(key, &mut blob[header.encoded_len()..])
+}
+
+/// A header field that differs between two envelopes, with the value from
+/// each side.
+// Kept unboxed and Copy like `Header`; diffs are only built for diagnostics.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderDifference {
+    /// The format versions differ.
+    Version(u8, u8),
+    /// The algorithms differ.
+    Algorithm(Algorithm, Algorithm),
+    /// The recorded key ids differ, or only one side records one.
+    KeyId(Option<KeyId>, Option<KeyId>),
+    /// Only one side was sealed under a per-message subkey.
+    Subkey(bool, bool),
+    /// Only one side is a cascade envelope.
+    Cascade(bool, bool),
+}
+
+impl fmt::Display for HeaderDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn key_id(id: &Option<KeyId>) -> String {
+            match id {
+                Some(id) => String::from_utf8_lossy(id.as_bytes()).into_owned(),
+                None => "none".to_string(),
+            }
+        }
+
+        match self {
+            HeaderDifference::Version(a, b) => write!(f, "version: {a} vs {b}"),
+            HeaderDifference::Algorithm(a, b) => {
+                write!(f, "algorithm: {} vs {}", a.name(), b.name())
+            }
+            HeaderDifference::KeyId(a, b) => write!(f, "key id: {} vs {}", key_id(a), key_id(b)),
+            HeaderDifference::Subkey(a, b) => write!(f, "per-message subkey: {a} vs {b}"),
+            HeaderDifference::Cascade(a, b) => write!(f, "cascade: {a} vs {b}"),
+        }
+    }
+}
+
+impl Header {
+    /// Lists the fields that differ between two headers.
+    ///
+    /// Nonces and tags differ between any two envelopes and are not compared.
+    pub fn diff(&self, other: &Header) -> Vec<HeaderDifference> {
+        let mut differences = Vec::new();
+        if self.version != other.version {
+            differences.push(HeaderDifference::Version(self.version, other.version));
+        }
+        if self.algorithm != other.algorithm {
+            differences.push(HeaderDifference::Algorithm(self.algorithm, other.algorithm));
+        }
+        if self.key_id != other.key_id {
+            differences.push(HeaderDifference::KeyId(self.key_id, other.key_id));
+        }
+        if self.subkey.is_some() != other.subkey.is_some() {
+            differences.push(HeaderDifference::Subkey(
+                self.subkey.is_some(),
+                other.subkey.is_some(),
+            ));
+        }
+        if self.is_cascade() != other.is_cascade() {
+            differences.push(HeaderDifference::Cascade(
+                self.is_cascade(),
+                other.is_cascade(),
+            ));
+        }
+        differences
+    }
+}
+
+/// Compares the headers of two encoded envelopes, without any key.
+///
+/// Helps to tell why a blob opens in one environment but not in another:
+/// a different algorithm or key id shows up here, whereas two envelopes that
+/// match field for field point at the key itself.
+///
+/// # Arguments
+///
+/// * `a` - The first encoded envelope.
+/// * `b` - The second encoded envelope.
+///
+/// # Returns
+///
+/// The differing fields, empty if the headers match, or the parse error of
+/// either blob.
+pub fn diff(a: &[u8], b: &[u8]) -> Result<Vec<HeaderDifference>, Error> {
+    Ok(Header::parse(a)?.diff(&Header::parse(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+
+        let blob = seal(Algorithm::Aes256Gcm, &key, b"hello world").expect("seal failed");
+        assert_eq!(blob.len(), MIN_HEADER_LEN + b"hello world".len());
+        assert_eq!(open(&key, &blob).expect("open failed"), b"hello world");
+
+        let envelope = Envelope::from_bytes(&blob).unwrap();
+        assert_eq!(envelope.header().version(), VERSION);
+        assert_eq!(envelope.header().algorithm(), Algorithm::Aes256Gcm);
+        assert_eq!(envelope.to_bytes(), blob);
+    }
+
+    #[test]
+    fn test_version_1_envelopes_still_open() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let (sealed, nonce) = cipher.encrypt_with_aad(b"hello world", &[1, 3]).unwrap();
+
+        let mut blob = vec![1, 3];
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&sealed[sealed.len() - TAG_LEN..]);
+        blob.extend_from_slice(&sealed[..sealed.len() - TAG_LEN]);
+
+        assert_eq!(open(&key, &blob).unwrap(), b"hello world");
+        assert_eq!(Envelope::from_bytes(&blob).unwrap().to_bytes(), blob);
+    }
+
+    #[test]
+    fn test_canonical_bytes() {
+        let mut prefix = vec![VERSION, Algorithm::Aes256Gcm.id(), 0x03];
+        prefix.extend_from_slice(&[0x11; SUBKEY_SALT_LEN]);
+        prefix.extend_from_slice(&[0x22; COMMITMENT_LEN]);
+        prefix.extend_from_slice(&[2, b'k', b'1']);
+        let mut blob = prefix.clone();
+        blob.extend_from_slice(&[0x33; NONCE_LEN + TAG_LEN]);
+
+        let header = Header::parse(&blob).unwrap();
+        assert_eq!(header.canonical_bytes(), prefix);
+        assert_eq!(header.encoded_len(), blob.len());
+
+        // The bytes every implementation must produce for that header.
+        let hex: String = prefix.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(
+            hex,
+            format!("020303{}{}026b31", "11".repeat(32), "22".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_canonical_bytes_are_exhaustively_unique() {
+        // Every header byte sequence that parses must re-encode to itself,
+        // whatever the version, algorithm, flags and key id length.
+        let mut accepted = 0;
+        for version in [0, 1, VERSION, VERSION + 1, 0xff] {
+            for algorithm in 0..=0xffu8 {
+                for flags in 0..=0xffu8 {
+                    for key_id_len in [0u8, 1, 0xff] {
+                        let mut blob = vec![version, algorithm, flags];
+                        blob.extend_from_slice(&[0x44; SUBKEY_SALT_LEN + COMMITMENT_LEN]);
+                        blob.push(key_id_len);
+                        blob.extend_from_slice(&[0x55; MAX_PREFIX_LEN + NONCE_LEN + TAG_LEN]);
+
+                        let Ok(header) = Header::parse(&blob) else {
+                            continue;
+                        };
+                        let canonical = header.canonical_bytes();
+                        assert_eq!(canonical, blob[..canonical.len()]);
+                        assert_eq!(Header::parse(&blob[..header.encoded_len()]), Ok(header));
+                        accepted += 1;
+                    }
+                }
+            }
+        }
+        // Version 1 ignores the flags byte. Version 2 accepts four flag
+        // combinations; only with both extensions does the key id length come
+        // from `key_id_len`, and then the empty id is rejected.
+        // The cascade bit doubles that, for AES-256-GCM only.
+        let algorithms = Algorithm::ALL.len();
+        assert_eq!(accepted, algorithms * (256 * 3 + 11) + 11);
+    }
+
+    #[test]
+    fn test_text_encodings() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+
+        for alphabet in [Base64Alphabet::Standard, Base64Alphabet::UrlSafe] {
+            let text =
+                seal_to_base64(Algorithm::Aes256Gcm, &key, b"hello world", alphabet).unwrap();
+            assert_eq!(
+                open_from_base64(&key, &text, alphabet).unwrap(),
+                b"hello world"
+            );
+            let envelope = Envelope::from_base64(&text, alphabet).unwrap();
+            assert_eq!(envelope.to_base64(alphabet), text);
+        }
+
+        let text = seal_to_hex(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+        assert_eq!(open_from_hex(&key, &text).unwrap(), b"hello world");
+        assert_eq!(
+            open_from_hex(&key, &text.to_uppercase()).unwrap(),
+            b"hello world"
+        );
+        assert_eq!(Envelope::from_hex(&text).unwrap().to_hex(), text);
+        assert_eq!(open_from_hex(&key, "02zz"), Err(Error::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_seal_cascade() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let blob = seal_cascade(&key, b"hello world").unwrap();
+        assert_eq!(
+            blob.len(),
+            MIN_HEADER_LEN + NONCE_LEN + b"hello world".len() + TAG_LEN
+        );
+        assert_eq!(open(&key, &blob).unwrap(), b"hello world");
+        assert_eq!(open_fixed::<11>(&key, &blob).unwrap(), *b"hello world");
+        let mut in_place = blob.clone();
+        assert_eq!(open_in_place(&key, &mut in_place).unwrap(), b"hello world");
+
+        let header = *Envelope::from_bytes(&blob).unwrap().header();
+        assert!(header.is_cascade());
+        assert_eq!(header.algorithm(), Algorithm::Aes256Gcm);
+
+        // Neither layer uses the master key itself: the same payload in a
+        // single-layer envelope does not open.
+        let stripped = [
+            &[VERSION, Algorithm::Aes256Gcm.id(), 0],
+            &blob[3 + NONCE_LEN..],
+        ]
+        .concat();
+        assert_eq!(open(&key, &stripped), Err(Error::DecryptionFailed));
+
+        // Flipping the inner nonce breaks both layers' associated data.
+        let mut tampered = blob.clone();
+        tampered[3] ^= 1;
+        assert_eq!(open(&key, &tampered), Err(Error::DecryptionFailed));
+
+        let mut chacha = blob;
+        chacha[1] = Algorithm::ChaCha20Poly1305.id();
+        assert_eq!(open(&key, &chacha), Err(Error::UnsupportedAlgorithm));
+    }
+
+    #[test]
+    fn test_seal_wrapped() {
+        let kek = Key::new(b"an example very very secret key.").unwrap();
+        let blob = seal_wrapped(&kek, b"hello world").unwrap();
+        let wrapped_len = MIN_HEADER_LEN + 32;
+        assert_eq!(blob[0] as usize, wrapped_len);
+        assert_eq!(open_wrapped(&kek, &blob).unwrap(), b"hello world");
+
+        // Every object gets its own data key.
+        let other = seal_wrapped(&kek, b"hello world").unwrap();
+        let dek = open(&kek, &blob[1..1 + wrapped_len]).unwrap();
+        assert_ne!(dek, open(&kek, &other[1..1 + wrapped_len]).unwrap());
+        assert_eq!(
+            open(&Key::new(&dek).unwrap(), &blob[1 + wrapped_len..]).unwrap(),
+            b"hello world"
+        );
+
+        // A data key wrapped for another object does not open this payload.
+        let swapped = [&other[..1 + wrapped_len], &blob[1 + wrapped_len..]].concat();
+        assert_eq!(open_wrapped(&kek, &swapped), Err(Error::DecryptionFailed));
+
+        let wrong = Key::new(&[7; 32]).unwrap();
+        assert_eq!(open_wrapped(&wrong, &blob), Err(Error::DecryptionFailed));
+        assert_eq!(open_wrapped(&kek, &blob[..40]), Err(Error::TruncatedInput));
+        assert_eq!(open_wrapped(&kek, &[]), Err(Error::TruncatedInput));
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let mut blob = seal(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+
+        blob[3] ^= 1;
+        assert_eq!(open(&key, &blob), Err(Error::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_malformed_envelopes() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let blob = seal(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+
+        assert_eq!(
+            open(&key, &blob[..MIN_HEADER_LEN - 1]),
+            Err(Error::TruncatedInput)
+        );
+        assert_eq!(open(&key, &[]), Err(Error::TruncatedInput));
+
+        let mut unknown_version = blob.clone();
+        unknown_version[0] = 3;
+        assert_eq!(open(&key, &unknown_version), Err(Error::UnsupportedVersion));
+
+        let mut unknown_algorithm = blob.clone();
+        unknown_algorithm[1] = 0xff;
+        assert_eq!(
+            open(&key, &unknown_algorithm),
+            Err(Error::UnsupportedAlgorithm)
+        );
+
+        let mut unknown_extension = blob;
+        unknown_extension[2] = 0x80;
+        assert_eq!(
+            open(&key, &unknown_extension),
+            Err(Error::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_seal_with_subkey() {
+        let master = Key::new(b"an example very very secret key.").unwrap();
+
+        let blob = seal_with_subkey(Algorithm::Aes256Gcm, &master, b"hello world").unwrap();
+        let envelope = Envelope::from_bytes(&blob).unwrap();
+        assert!(envelope.header().subkey().is_some());
+        assert_eq!(
+            envelope.header().encoded_len(),
+            MIN_HEADER_LEN + SUBKEY_SALT_LEN + COMMITMENT_LEN
+        );
+        assert_eq!(open(&master, &blob).unwrap(), b"hello world");
+
+        // Each message gets its own salt.
+        let again = seal_with_subkey(Algorithm::Aes256Gcm, &master, b"hello world").unwrap();
+        assert_ne!(blob[3..3 + SUBKEY_SALT_LEN], again[3..3 + SUBKEY_SALT_LEN]);
+
+        let other = Key::new(b"another example very secret key.").unwrap();
+        assert_eq!(open(&other, &blob), Err(Error::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_decrypt_fixed() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let blob = seal(Algorithm::Aes256Gcm, &key, b"0123456789abcdef").unwrap();
+
+        let record: [u8; 16] = open_fixed(&key, &blob).expect("open failed");
+        assert_eq!(&record, b"0123456789abcdef");
+
+        let envelope = Envelope::from_bytes(&blob).unwrap();
+        assert_eq!(decrypt_fixed::<16>(&key, &envelope).unwrap(), record);
+        assert_eq!(
+            decrypt_fixed::<8>(&key, &envelope),
+            Err(Error::InvalidPlaintextLength {
+                expected: 8,
+                actual: 16
+            })
+        );
+
+        let mut tampered = blob;
+        tampered[MIN_HEADER_LEN] ^= 1;
+        assert_eq!(
+            open_fixed::<16>(&key, &tampered),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_in_place_round_trip() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let packet: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+
+        let mut buf = Vec::with_capacity(packet.len() + MIN_HEADER_LEN);
+        buf.extend_from_slice(&packet);
+        seal_in_place(&cipher, &mut buf).unwrap();
+        assert_eq!(open(&key, &buf).unwrap(), packet);
+        assert_eq!(open_in_place(&key, &mut buf).unwrap(), &packet[..]);
+
+        let mut tampered = seal(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+        tampered[MIN_HEADER_LEN] ^= 1;
+        assert_eq!(
+            open_in_place(&key, &mut tampered),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_seal_with_strategy() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let mut nonces = crate::nonce::FixedNonce::new([7; NONCE_LEN]);
+
+        let envelope = Envelope::seal_with_strategy(&cipher, &mut nonces, b"hello world").unwrap();
+        assert_eq!(envelope.header().nonce(), &[7; NONCE_LEN]);
+        assert_eq!(open(&key, &envelope.to_bytes()).unwrap(), b"hello world");
+        assert_eq!(
+            Envelope::seal_with_strategy(&cipher, &mut nonces, b"hello world").err(),
+            Some(Error::NonceExhausted)
+        );
+    }
+
+    #[test]
+    fn test_seal_vectored() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let body = vec![7u8; 64 * 1024];
+        let parts: [&[u8]; 4] = [b"header:", &body, b"", b":trailer"];
+
+        let blob = seal_vectored(&cipher, &parts).unwrap();
+        assert_eq!(blob.len(), MIN_HEADER_LEN + parts.concat().len());
+        assert_eq!(open(&key, &blob).unwrap(), parts.concat());
+        assert_eq!(
+            open(&key, &seal_vectored(&cipher, &[]).unwrap()).unwrap(),
+            b""
+        );
+    }
+
+    #[test]
+    fn test_diff_headers() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let aes = seal(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+        let chacha = seal_with_subkey(Algorithm::ChaCha20Poly1305, &key, b"hello world").unwrap();
+
+        assert_eq!(
+            diff(&aes, &seal(Algorithm::Aes256Gcm, &key, b"other").unwrap()),
+            Ok(vec![])
+        );
+
+        let differences = diff(&aes, &chacha).unwrap();
+        assert_eq!(
+            differences,
+            [
+                HeaderDifference::Algorithm(Algorithm::Aes256Gcm, Algorithm::ChaCha20Poly1305),
+                HeaderDifference::Subkey(false, true),
+            ]
+        );
+        assert_eq!(
+            differences[0].to_string(),
+            "algorithm: AES-256-GCM vs ChaCha20-Poly1305"
+        );
+
+        let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+        let tagged = Envelope::seal_with_key_id(&cipher, &KeyId::new(b"k1").unwrap(), b"")
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            diff(&aes, &tagged).unwrap()[0].to_string(),
+            "key id: none vs k1"
+        );
+        assert_eq!(diff(&aes, &[]), Err(Error::TruncatedInput));
+    }
+}
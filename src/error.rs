@@ -0,0 +1,356 @@
+use alloc::boxed::Box;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Errors returned by the encryption and decryption functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The key does not have the length required by the algorithm.
+    InvalidKeyLength { expected: usize, actual: usize },
+    /// The nonce is not `aead::NONCE_LEN` bytes long.
+    InvalidNonce,
+    /// The key identifier is empty, too long to encode, or already in use.
+    InvalidKeyId,
+    /// The envelope names no key, or a key the keyring does not hold.
+    UnknownKeyId,
+    /// The algorithm is unknown or not available in this build.
+    UnsupportedAlgorithm,
+    /// The envelope was written by an unknown version of the format.
+    UnsupportedVersion,
+    /// The input ends before a complete envelope was read.
+    TruncatedInput,
+    /// The plaintext does not have the length the caller requires.
+    InvalidPlaintextLength { expected: usize, actual: usize },
+    /// The password KDF parameters are outside the accepted bounds.
+    InvalidKdfParams,
+    /// The stream segment size, content-encoding record size or ring buffer
+    /// layout is out of range.
+    InvalidSegmentSize,
+    /// The text encoding of a ciphertext or plaintext is malformed.
+    InvalidEncoding,
+    /// Every nonce available under the key has been used; encrypting more
+    /// would repeat one.
+    NonceExhausted,
+    /// The ciphertext could not be authenticated: the key is wrong, or the
+    /// ciphertext, tag, nonce or associated data was modified.
+    DecryptionFailed,
+    /// The plaintext is too long for the algorithm.
+    EncryptionFailed,
+    /// The system random number generator failed.
+    RandomFailed,
+    /// The encryption service has shut down and accepts no more jobs.
+    ServiceUnavailable,
+    /// The key share threshold or count is out of range, or the shares are
+    /// duplicated, inconsistent or fewer than the threshold.
+    InvalidShares,
+    /// A signature or MAC does not verify under the expected key.
+    InvalidSignature,
+    /// The key material is not 16, 24 or 32 bytes long, so no supported
+    /// algorithm accepts it.
+    UnsupportedKeyLength { actual: usize },
+}
+
+/// A coarse classification of [`Error`]s, for callers that branch on the
+/// kind of failure rather than on each variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An argument supplied by the caller is out of range: a key, nonce, key
+    /// id, length or parameter set.
+    InvalidArgument,
+    /// The ciphertext or its text encoding is malformed.
+    MalformedInput,
+    /// The algorithm or format version is not available in this build.
+    Unsupported,
+    /// The key needed to decrypt is not available.
+    KeyNotFound,
+    /// A tag, MAC or signature does not verify.
+    AuthenticationFailed,
+    /// A limit of the key or algorithm has been reached.
+    LimitExceeded,
+    /// A system resource or the encryption service is unavailable.
+    Unavailable,
+}
+
+impl Error {
+    /// Returns the kind of failure.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::InvalidKeyLength { .. }
+            | Error::UnsupportedKeyLength { .. }
+            | Error::InvalidNonce
+            | Error::InvalidKeyId
+            | Error::InvalidPlaintextLength { .. }
+            | Error::InvalidKdfParams
+            | Error::InvalidSegmentSize
+            | Error::InvalidShares => ErrorKind::InvalidArgument,
+            Error::TruncatedInput | Error::InvalidEncoding => ErrorKind::MalformedInput,
+            Error::UnsupportedAlgorithm | Error::UnsupportedVersion => ErrorKind::Unsupported,
+            Error::UnknownKeyId => ErrorKind::KeyNotFound,
+            Error::DecryptionFailed | Error::InvalidSignature => ErrorKind::AuthenticationFailed,
+            Error::NonceExhausted | Error::EncryptionFailed => ErrorKind::LimitExceeded,
+            Error::RandomFailed | Error::ServiceUnavailable => ErrorKind::Unavailable,
+        }
+    }
+
+    /// Returns the stable numeric code of the error.
+    ///
+    /// Codes are never reused or renumbered, so bindings for other languages
+    /// can map them to their own error types. New variants get new codes.
+    pub fn code(&self) -> u16 {
+        match self {
+            Error::InvalidKeyLength { .. } => 1,
+            Error::InvalidNonce => 2,
+            Error::InvalidKeyId => 3,
+            Error::UnknownKeyId => 4,
+            Error::UnsupportedAlgorithm => 5,
+            Error::UnsupportedVersion => 6,
+            Error::TruncatedInput => 7,
+            Error::InvalidPlaintextLength { .. } => 8,
+            Error::InvalidKdfParams => 9,
+            Error::InvalidSegmentSize => 10,
+            Error::InvalidEncoding => 11,
+            Error::NonceExhausted => 12,
+            Error::DecryptionFailed => 13,
+            Error::EncryptionFailed => 14,
+            Error::RandomFailed => 15,
+            Error::ServiceUnavailable => 16,
+            Error::InvalidShares => 17,
+            Error::InvalidSignature => 18,
+            Error::UnsupportedKeyLength { .. } => 19,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidKeyLength { expected, actual } => {
+                write!(
+                    f,
+                    "invalid key length: expected {expected} bytes, got {actual}"
+                )
+            }
+            Error::InvalidNonce => f.write_str("invalid nonce length"),
+            Error::InvalidKeyId => f.write_str("invalid key identifier"),
+            Error::UnknownKeyId => f.write_str("unknown key identifier"),
+            Error::UnsupportedAlgorithm => f.write_str("algorithm not supported by this build"),
+            Error::UnsupportedVersion => f.write_str("unsupported envelope version"),
+            Error::TruncatedInput => f.write_str("input is truncated"),
+            Error::InvalidPlaintextLength { expected, actual } => {
+                write!(
+                    f,
+                    "invalid plaintext length: expected {expected} bytes, got {actual}"
+                )
+            }
+            Error::InvalidKdfParams => f.write_str("KDF parameters out of bounds"),
+            Error::InvalidSegmentSize => f.write_str("invalid segment or record size"),
+            Error::InvalidEncoding => f.write_str("malformed text encoding"),
+            Error::NonceExhausted => f.write_str("nonces exhausted for this key"),
+            Error::DecryptionFailed => {
+                f.write_str("decryption failed: authentication tag mismatch")
+            }
+            Error::EncryptionFailed => f.write_str("encryption failed: plaintext too long"),
+            Error::RandomFailed => f.write_str("random number generator failed"),
+            Error::ServiceUnavailable => f.write_str("encryption service is not running"),
+            Error::InvalidShares => f.write_str("invalid or insufficient key shares"),
+            Error::InvalidSignature => f.write_str("signature verification failed"),
+            Error::UnsupportedKeyLength { actual } => {
+                write!(
+                    f,
+                    "invalid key length: expected 16, 24 or 32 bytes, got {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// An [`Error`] annotated with what was being done when it occurred, and
+/// optionally the lower-level error that caused it.
+///
+/// `Error` itself stays a small `Copy` value that callers can match on.
+/// Wrap it in a `Contextual` at the boundary where the context is known,
+/// e.g. with [`ResultExt::context`]:
+///
+/// ```
+/// use aes_implementation::error::ResultExt;
+/// use aes_implementation::{envelope, Error, Key};
+///
+/// let key = Key::new(&[1; 32]).unwrap();
+/// let err = envelope::open(&key, &[2])
+///     .context("opening the config blob")
+///     .unwrap_err();
+/// assert_eq!(err.error(), Error::TruncatedInput);
+/// assert_eq!(err.to_string(), "opening the config blob: input is truncated");
+/// ```
+#[derive(Debug)]
+pub struct Contextual {
+    error: Error,
+    context: &'static str,
+    source: Option<Box<dyn core::error::Error + Send + Sync>>,
+}
+
+impl Contextual {
+    /// Annotates an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error.
+    /// * `context` - What was being done, e.g. `"decrypting backup index"`.
+    pub fn new(error: Error, context: &'static str) -> Contextual {
+        Contextual {
+            error,
+            context,
+            source: None,
+        }
+    }
+
+    /// Records the lower-level error that caused this one, e.g. the I/O or
+    /// backend error behind an `Error::ServiceUnavailable`. It is returned by
+    /// [`source`](core::error::Error::source) in place of the wrapped `Error`.
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn core::error::Error + Send + Sync>>,
+    ) -> Contextual {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Returns the wrapped error.
+    pub fn error(&self) -> Error {
+        self.error
+    }
+
+    /// Returns the context the error was annotated with.
+    pub fn context(&self) -> &'static str {
+        self.context
+    }
+
+    /// Returns the kind of the wrapped error.
+    pub fn kind(&self) -> ErrorKind {
+        self.error.kind()
+    }
+
+    /// Returns the stable numeric code of the wrapped error.
+    pub fn code(&self) -> u16 {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for Contextual {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.error)
+    }
+}
+
+impl core::error::Error for Contextual {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match &self.source {
+            Some(source) => Some(source.as_ref()),
+            None => Some(&self.error),
+        }
+    }
+}
+
+impl From<Contextual> for Error {
+    fn from(err: Contextual) -> Self {
+        err.error
+    }
+}
+
+/// Adds [`Contextual`] annotations to results.
+pub trait ResultExt<T> {
+    /// Annotates the error, if any, with what was being done.
+    fn context(self, context: &'static str) -> Result<T, Contextual>;
+}
+
+impl<T> ResultExt<T> for Result<T, Error> {
+    fn context(self, context: &'static str) -> Result<T, Contextual> {
+        self.map_err(|error| Contextual::new(error, context))
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Contextual> for io::Error {
+    fn from(err: Contextual) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique() {
+        let errors = [
+            Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16,
+            },
+            Error::InvalidNonce,
+            Error::InvalidKeyId,
+            Error::UnknownKeyId,
+            Error::UnsupportedAlgorithm,
+            Error::UnsupportedVersion,
+            Error::TruncatedInput,
+            Error::InvalidPlaintextLength {
+                expected: 8,
+                actual: 4,
+            },
+            Error::InvalidKdfParams,
+            Error::InvalidSegmentSize,
+            Error::InvalidEncoding,
+            Error::NonceExhausted,
+            Error::DecryptionFailed,
+            Error::EncryptionFailed,
+            Error::RandomFailed,
+            Error::ServiceUnavailable,
+            Error::InvalidShares,
+            Error::InvalidSignature,
+            Error::UnsupportedKeyLength { actual: 20 },
+        ];
+        let codes: Vec<u16> = errors.iter().map(Error::code).collect();
+        assert_eq!(codes, (1..=errors.len() as u16).collect::<Vec<_>>());
+
+        assert_eq!(Error::TruncatedInput.kind(), ErrorKind::MalformedInput);
+        assert_eq!(
+            Error::InvalidSignature.kind(),
+            ErrorKind::AuthenticationFailed
+        );
+    }
+
+    #[test]
+    fn test_context_chain() {
+        use core::error::Error as _;
+
+        let err = Err::<(), _>(Error::ServiceUnavailable)
+            .context("sealing audit log")
+            .unwrap_err();
+        assert_eq!(err.code(), 16);
+        assert_eq!(err.kind(), ErrorKind::Unavailable);
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "encryption service is not running"
+        );
+
+        let err = err.with_source(std::io::Error::other("connection reset"));
+        assert_eq!(
+            err.to_string(),
+            "sealing audit log: encryption service is not running"
+        );
+        assert_eq!(err.source().unwrap().to_string(), "connection reset");
+        assert_eq!(Error::from(err), Error::ServiceUnavailable);
+    }
+}
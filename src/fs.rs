@@ -0,0 +1,217 @@
+//! File-to-file encryption on top of the streaming format.
+//!
+//! Encrypted files use the [`stream`](crate::stream) format, so they are
+//! self-describing: the header records the algorithm and segment size and
+//! only the key is needed to decrypt.
+//!
+//! Output is written to a temporary file next to the destination, synced and
+//! then renamed over it. The destination therefore either keeps its old
+//! contents or holds the complete result, and a decrypted file only appears
+//! once every segment has been authenticated.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::aead::Algorithm;
+use crate::encoding;
+use crate::key::Key;
+use crate::rng;
+use crate::stream::{StreamDecryptor, StreamEncryptor};
+
+/// Encrypts a file with AES-256-GCM.
+///
+/// # Arguments
+///
+/// * `input` - The plaintext file.
+/// * `output` - The destination; replaced atomically if it exists.
+/// * `key` - A 256-bit key.
+pub fn encrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    key: &Key,
+) -> io::Result<()> {
+    encrypt_file_with(input, output, Algorithm::Aes256Gcm, key)
+}
+
+/// Encrypts a file with the given algorithm.
+///
+/// # Arguments
+///
+/// * `input` - The plaintext file.
+/// * `output` - The destination; replaced atomically if it exists.
+/// * `algorithm` - The AEAD algorithm to use.
+/// * `key` - The key; its length must match the algorithm.
+pub fn encrypt_file_with<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    algorithm: Algorithm,
+    key: &Key,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    write_atomically(output.as_ref(), |file| {
+        let mut encryptor = StreamEncryptor::new(BufWriter::new(file), algorithm, key)?;
+        io::copy(&mut reader, &mut encryptor)?;
+        encryptor.finish()?.flush()
+    })
+}
+
+/// Decrypts a file produced by [`encrypt_file`] or [`encrypt_file_with`].
+///
+/// # Arguments
+///
+/// * `input` - The encrypted file.
+/// * `output` - The destination; only created once the whole input has been
+///   authenticated.
+/// * `key` - The key the file was encrypted with.
+///
+/// # Returns
+///
+/// An error of kind `InvalidData` if the input was modified, truncated or
+/// encrypted under another key. The destination is left untouched then.
+pub fn decrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    key: &Key,
+) -> io::Result<()> {
+    let mut decryptor = StreamDecryptor::new(BufReader::new(File::open(input)?), key)?;
+    write_atomically(output.as_ref(), |file| {
+        let mut writer = BufWriter::new(file);
+        io::copy(&mut decryptor, &mut writer)?;
+        writer.flush()
+    })
+}
+
+/// Writes a file atomically: runs `write` against a temporary file beside
+/// `output`, syncs it and renames it into place.
+///
+/// On unix the temporary file is created with mode `0600`, so the new
+/// contents are never readable by other users, and the directory is synced
+/// after the rename so the new entry survives a crash.
+///
+/// # Arguments
+///
+/// * `output` - The destination; replaced only if `write` succeeds.
+/// * `write` - Writes the new contents.
+///
+/// # Returns
+///
+/// The first error from `write`, the sync or the rename. The temporary file
+/// is removed then and the destination is left untouched. An error from
+/// syncing the directory is returned after the rename has taken place.
+pub fn write_atomically(
+    output: &Path,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp = temp_path(output)?;
+    let result = create_temp(&temp).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(err) = result.and_then(|()| fs::rename(&temp, output)) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    sync_parent(output)
+}
+
+#[cfg(unix)]
+fn create_temp(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_temp(path: &Path) -> io::Result<File> {
+    File::create_new(path)
+}
+
+#[cfg(unix)]
+fn sync_parent(output: &Path) -> io::Result<()> {
+    let parent = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+// Directories cannot be opened as files elsewhere; the rename is all we get.
+#[cfg(not(unix))]
+fn sync_parent(_output: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn temp_path(output: &Path) -> io::Result<PathBuf> {
+    let name = output.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "output path has no file name")
+    })?;
+
+    let mut suffix = [0u8; 8];
+    rng::fill(&mut suffix)?;
+    let suffix = encoding::encode_hex(&suffix);
+
+    let mut temp = name.to_os_string();
+    temp.push(format!(".{suffix}.tmp"));
+    Ok(output.with_file_name(temp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let mut suffix = [0u8; 8];
+        rng::fill(&mut suffix).unwrap();
+        let name = encoding::encode_hex(&suffix);
+        let dir = std::env::temp_dir().join(format!("aes_implementation-fs-{name}"));
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_round_trip_and_atomic_failure() {
+        let dir = scratch_dir();
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let plaintext: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+        fs::write(dir.join("plain"), &plaintext).unwrap();
+
+        encrypt_file(dir.join("plain"), dir.join("sealed"), &key).unwrap();
+        decrypt_file(dir.join("sealed"), dir.join("opened"), &key).unwrap();
+        assert_eq!(fs::read(dir.join("opened")).unwrap(), plaintext);
+
+        // A tampered file must not replace the destination or leave a
+        // temporary file behind.
+        let mut sealed = fs::read(dir.join("sealed")).unwrap();
+        sealed[100_000] ^= 1;
+        fs::write(dir.join("sealed"), &sealed).unwrap();
+        fs::write(dir.join("opened"), b"previous").unwrap();
+
+        let err = decrypt_file(dir.join("sealed"), dir.join("opened"), &key).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read(dir.join("opened")).unwrap(), b"previous");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_is_private_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir();
+        let output = dir.join("secret");
+        write_atomically(&output, |file| file.write_all(b"contents")).unwrap();
+
+        let mode = fs::metadata(&output).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read(&output).unwrap(), b"contents");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! HKDF-SHA256 key derivation.
+//!
+//! Use [`derive_key`] to split one master key into independent per-purpose
+//! subkeys instead of reusing the same key everywhere:
+//!
+//! ```
+//! use aes_implementation::{kdf, Key};
+//!
+//! let master = Key::new(b"an example very very secret key.").unwrap();
+//! let records = kdf::derive_key(&master, b"", b"records").unwrap();
+//! let backups = kdf::derive_key(&master, b"", b"backups").unwrap();
+//! assert_ne!(records.as_bytes(), backups.as_bytes());
+//! ```
+
+use ring::hkdf::{KeyType, Prk, Salt, HKDF_SHA256};
+use zeroize::Zeroizing;
+
+use crate::aead::Algorithm;
+use crate::error::Error;
+use crate::key::Key;
+
+/// Length in bytes of the random salt used for per-message subkeys.
+pub const SUBKEY_SALT_LEN: usize = 32;
+
+/// Length in bytes of the key commitment stored next to a per-message salt.
+pub const COMMITMENT_LEN: usize = 32;
+
+const SUBKEY_INFO: &[u8] = b"aes_implementation/envelope/subkey";
+const COMMITMENT_INFO: &[u8] = b"aes_implementation/envelope/commitment";
+const RECORD_INFO: &[u8] = b"aes_implementation/record";
+const CASCADE_OUTER_INFO: &[u8] = b"aes_implementation/envelope/cascade/aes-256-gcm";
+const CASCADE_INNER_INFO: &[u8] = b"aes_implementation/envelope/cascade/chacha20-poly1305";
+const DETERMINISTIC_ENCRYPTION_INFO: &[u8] = b"aes_implementation/deterministic/encryption";
+const DETERMINISTIC_NONCE_INFO: &[u8] = b"aes_implementation/deterministic/nonce";
+
+/// Output length marker for HKDF expansion into a raw byte buffer.
+pub(crate) struct OkmLen(pub(crate) usize);
+
+impl KeyType for OkmLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// Derives a subkey from a master key with HKDF-SHA256.
+///
+/// # Arguments
+///
+/// * `master` - The master key.
+/// * `salt` - An optional salt; may be empty.
+/// * `info` - A label binding the subkey to its purpose.
+///
+/// # Returns
+///
+/// A key of the same length as `master`. Different `salt` or `info` values
+/// yield independent keys.
+pub fn derive_key(master: &Key, salt: &[u8], info: &[u8]) -> Result<Key, Error> {
+    let mut out = Zeroizing::new([0u8; 32]);
+    let out = &mut out[..master.as_bytes().len()];
+    expand(&extract(master.as_bytes(), salt), &[info], out)?;
+    Key::new(out)
+}
+
+/// Derives the per-message key and key commitment for an envelope.
+///
+/// The commitment is stored in the envelope and checked before decrypting,
+/// so a ciphertext can only ever be opened under the master key it was
+/// sealed with.
+pub(crate) fn derive_message_key(
+    algorithm: Algorithm,
+    master: &Key,
+    salt: &[u8; SUBKEY_SALT_LEN],
+) -> Result<(Key, [u8; COMMITMENT_LEN]), Error> {
+    let prk = extract(master.as_bytes(), salt);
+    let id = [algorithm.id()];
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    let key = &mut key[..algorithm.key_len()];
+    expand(&prk, &[SUBKEY_INFO, &id], key)?;
+
+    let mut commitment = [0u8; COMMITMENT_LEN];
+    expand(&prk, &[COMMITMENT_INFO, &id], &mut commitment)?;
+
+    Ok((Key::for_algorithm(algorithm, key)?, commitment))
+}
+
+/// Derives the key for one database record from the tenant master key.
+pub(crate) fn derive_record_key(
+    algorithm: Algorithm,
+    master: &Key,
+    row_id: &[u8],
+) -> Result<Key, Error> {
+    let prk = extract(master.as_bytes(), &[]);
+    let mut key = Zeroizing::new([0u8; 32]);
+    let key = &mut key[..algorithm.key_len()];
+    expand(&prk, &[RECORD_INFO, &[algorithm.id()], row_id], key)?;
+    Key::for_algorithm(algorithm, key)
+}
+
+/// Derives the two independent keys of a cascade envelope: the outer
+/// AES-256-GCM key and the inner ChaCha20-Poly1305 key.
+pub(crate) fn derive_cascade_keys(master: &Key) -> Result<(Key, Key), Error> {
+    Algorithm::Aes256Gcm.check_key_len(master.as_bytes().len())?;
+    let prk = extract(master.as_bytes(), &[]);
+
+    let mut outer = Zeroizing::new([0u8; 32]);
+    expand(&prk, &[CASCADE_OUTER_INFO], &mut outer[..])?;
+    let mut inner = Zeroizing::new([0u8; 32]);
+    expand(&prk, &[CASCADE_INNER_INFO], &mut inner[..])?;
+
+    Ok((
+        Key::for_algorithm(Algorithm::Aes256Gcm, &outer[..])?,
+        Key::for_algorithm(Algorithm::ChaCha20Poly1305, &inner[..])?,
+    ))
+}
+
+/// Derives the two independent keys of a deterministic cipher: the
+/// AES-256-GCM key and the HMAC-SHA256 key its synthetic nonces come from.
+pub(crate) fn derive_deterministic_keys(master: &Key) -> Result<(Key, Key), Error> {
+    Algorithm::Aes256Gcm.check_key_len(master.as_bytes().len())?;
+    let prk = extract(master.as_bytes(), &[]);
+
+    let mut encryption = Zeroizing::new([0u8; 32]);
+    expand(&prk, &[DETERMINISTIC_ENCRYPTION_INFO], &mut encryption[..])?;
+    let mut nonce = Zeroizing::new([0u8; 32]);
+    expand(&prk, &[DETERMINISTIC_NONCE_INFO], &mut nonce[..])?;
+
+    Ok((
+        Key::for_algorithm(Algorithm::Aes256Gcm, &encryption[..])?,
+        Key::new(&nonce[..])?,
+    ))
+}
+
+/// TLS 1.3 HKDF-Expand-Label (RFC 8446, section 7.1) over HKDF-SHA256.
+///
+/// `secret` must already be a pseudorandom key, such as a traffic secret.
+pub(crate) fn expand_label(
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+    out: &mut [u8],
+) -> Result<(), Error> {
+    let prk = Prk::new_less_safe(HKDF_SHA256, secret);
+    let len = (out.len() as u16).to_be_bytes();
+    let label_len = [(b"tls13 ".len() + label.len()) as u8];
+    let context_len = [context.len() as u8];
+    expand(
+        &prk,
+        &[&len, &label_len, b"tls13 ", label, &context_len, context],
+        out,
+    )
+}
+
+fn extract(ikm: &[u8], salt: &[u8]) -> Prk {
+    Salt::new(HKDF_SHA256, salt).extract(ikm)
+}
+
+// HKDF-SHA256 can expand to at most 255 * 32 bytes.
+fn expand(prk: &Prk, info: &[&[u8]], out: &mut [u8]) -> Result<(), Error> {
+    prk.expand(info, OkmLen(out.len()))
+        .and_then(|okm| okm.fill(out))
+        .map_err(|_| Error::InvalidKeyLength {
+            expected: 255 * 32,
+            actual: out.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf_matches_rfc_5869() {
+        // RFC 5869, test case 1. The 22-byte IKM is not a valid key length,
+        // so this goes through the internal helpers.
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let mut okm = [0u8; 42];
+        expand(&extract(&[0x0b; 22], &salt), &[&info], &mut okm).unwrap();
+
+        let expected = [
+            0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+            0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+            0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+        ];
+        assert_eq!(okm, expected);
+    }
+
+    #[test]
+    fn test_derive_key_separates_labels() {
+        let master = Key::new(b"an example very very secret key.").unwrap();
+        let subkey = derive_key(&master, b"salt", b"a").unwrap();
+
+        assert_eq!(subkey.as_bytes().len(), 32);
+        assert_eq!(
+            subkey.as_bytes(),
+            derive_key(&master, b"salt", b"a").unwrap().as_bytes()
+        );
+        assert_ne!(
+            subkey.as_bytes(),
+            derive_key(&master, b"salt", b"b").unwrap().as_bytes()
+        );
+        assert_ne!(
+            subkey.as_bytes(),
+            derive_key(&master, b"pepper", b"a").unwrap().as_bytes()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use ring::constant_time;
+use ring::rand::{SecureRandom, SystemRandom};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::aead::Algorithm;
+use crate::error::Error;
+
+/// Length in bytes of an AES-256 key.
+pub const KEY_LEN: usize = 32;
+
+/// A symmetric key of 128, 192 or 256 bits.
+///
+/// The length is checked against the algorithm when the key is used to build a
+/// [`Cipher`](crate::aead::Cipher).
+///
+/// The key material is overwritten with zeros when the key is dropped,
+/// equality is checked in constant time, and `Debug` only shows the length.
+#[derive(Clone)]
+pub struct Key(Vec<u8>);
+
+impl Key {
+    /// Generates a random 256-bit key.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The system random number generator.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::RandomFailed` if the generator failed.
+    pub fn generate(rng: &SystemRandom) -> Result<Key, Error> {
+        Key::generate_for(Algorithm::Aes256Gcm, rng)
+    }
+
+    /// Generates a random key of the length the given algorithm requires.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm the key will be used with.
+    /// * `rng` - The system random number generator.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::RandomFailed` if the generator failed.
+    pub fn generate_for(algorithm: Algorithm, rng: &SystemRandom) -> Result<Key, Error> {
+        let mut key = Key(vec![0; algorithm.key_len()]);
+        rng.fill(&mut key.0).map_err(|_| Error::RandomFailed)?;
+        Ok(key)
+    }
+
+    /// Creates a key from raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The key material; must be 16, 24 or 32 bytes long.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::UnsupportedKeyLength` if `bytes` is not 16, 24 or
+    /// 32 bytes long. [`Key::for_algorithm`] checks against one algorithm.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Key, Error> {
+        match bytes.len() {
+            16 | 24 | KEY_LEN => Ok(Key(bytes.to_vec())),
+            actual => Err(Error::UnsupportedKeyLength { actual }),
+        }
+    }
+
+    /// Creates a key from raw bytes; the same as [`Key::from_bytes`].
+    pub fn new(bytes: &[u8]) -> Result<Key, Error> {
+        Key::from_bytes(bytes)
+    }
+
+    /// Creates a key for the given algorithm from raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The algorithm the key will be used with.
+    /// * `bytes` - The key material; must be `algorithm.key_len()` bytes long.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::InvalidKeyLength` if `bytes` has the wrong length.
+    pub fn for_algorithm(algorithm: Algorithm, bytes: &[u8]) -> Result<Key, Error> {
+        algorithm.check_key_len(bytes.len())?;
+        Ok(Key(bytes.to_vec()))
+    }
+
+    /// Returns the raw key material.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns a copy of the raw key material, e.g. to store it elsewhere.
+    /// The copy is zeroized when dropped, too.
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.0.clone())
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        constant_time::verify_slices_are_equal(&self.0, &other.0).is_ok()
+    }
+}
+
+impl Eq for Key {}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key").field("len", &self.0.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_round_trip_bytes() {
+        let rng = SystemRandom::new();
+        let key = Key::generate(&rng).unwrap();
+        assert_eq!(key.as_bytes().len(), KEY_LEN);
+        assert_ne!(key, Key::generate(&rng).unwrap());
+        assert_eq!(
+            Key::generate_for(Algorithm::Aes128Gcm, &rng)
+                .unwrap()
+                .as_bytes()
+                .len(),
+            16
+        );
+
+        let copy = Key::from_bytes(&key.to_bytes()).unwrap();
+        assert_eq!(copy, key);
+        assert_eq!(format!("{copy:?}"), "Key { len: 32 }");
+
+        assert_eq!(
+            Key::from_bytes(&[0; 31]),
+            Err(Error::UnsupportedKeyLength { actual: 31 })
+        );
+    }
+}
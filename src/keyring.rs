@@ -0,0 +1,319 @@
+//! Key rotation with key ids recorded in the envelope.
+//!
+//! A [`Keyring`] holds every key that may still be needed to decrypt, each
+//! under a short id such as `"2026-10"`. [`Keyring::encrypt`] seals under the
+//! active key and writes its id into the envelope header; [`Keyring::decrypt`]
+//! reads the id back and picks the matching key, so ciphertexts stay readable
+//! after a rotation for as long as their key is kept in the ring.
+//!
+//! ```
+//! use aes_implementation::keyring::Keyring;
+//! use aes_implementation::{Algorithm, Key};
+//!
+//! let mut keyring = Keyring::new();
+//! keyring.add(b"2026-09", Algorithm::Aes256Gcm, &Key::new(&[1; 32]).unwrap()).unwrap();
+//! let old = keyring.encrypt(b"hello world").unwrap();
+//!
+//! // Rotate: new ciphertexts use the new key, old ones still open.
+//! keyring.add(b"2026-10", Algorithm::Aes256Gcm, &Key::new(&[2; 32]).unwrap()).unwrap();
+//! assert_eq!(keyring.decrypt(&old).unwrap(), b"hello world");
+//!
+//! let migrated = keyring.reencrypt(&old).unwrap();
+//! assert_eq!(Keyring::key_id(&migrated).unwrap().as_bytes(), b"2026-10");
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::aead::{Algorithm, Cipher};
+use crate::envelope::Envelope;
+use crate::error::Error;
+use crate::key::Key;
+
+/// Longest key id that fits in an envelope header.
+pub const MAX_KEY_ID_LEN: usize = 255;
+
+/// The id of a key in a [`Keyring`]: between 1 and [`MAX_KEY_ID_LEN`] bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyId {
+    bytes: [u8; MAX_KEY_ID_LEN],
+    len: u8,
+}
+
+impl KeyId {
+    /// Creates a key id.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id; must be between 1 and [`MAX_KEY_ID_LEN`] bytes long.
+    ///
+    /// # Returns
+    ///
+    /// The key id, or `Error::InvalidKeyId` if `id` is empty or too long.
+    pub fn new(id: &[u8]) -> Result<KeyId, Error> {
+        if id.is_empty() || id.len() > MAX_KEY_ID_LEN {
+            return Err(Error::InvalidKeyId);
+        }
+        let mut bytes = [0u8; MAX_KEY_ID_LEN];
+        bytes[..id.len()].copy_from_slice(id);
+        Ok(KeyId {
+            bytes,
+            len: id.len() as u8,
+        })
+    }
+
+    /// Returns the raw id.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl fmt::Debug for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("KeyId")
+            .field(&String::from_utf8_lossy(self.as_bytes()))
+            .finish()
+    }
+}
+
+struct Entry {
+    id: KeyId,
+    algorithm: Algorithm,
+    key: Key,
+}
+
+/// A set of keys tagged with ids, one of which is active for encryption.
+#[derive(Default)]
+pub struct Keyring {
+    entries: Vec<Entry>,
+    active: Option<usize>,
+}
+
+impl Keyring {
+    /// Creates an empty keyring.
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    /// Adds a key and makes it the active one.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id to record in envelopes sealed with this key.
+    /// * `algorithm` - The AEAD algorithm to use with this key.
+    /// * `key` - The key; its length must match the algorithm.
+    ///
+    /// # Returns
+    ///
+    /// `Error::InvalidKeyId` if the id is malformed or already in the ring,
+    /// or the error [`Cipher::new`] returns for the algorithm and key.
+    pub fn add(&mut self, id: &[u8], algorithm: Algorithm, key: &Key) -> Result<(), Error> {
+        let id = KeyId::new(id)?;
+        if self.position(&id).is_some() {
+            return Err(Error::InvalidKeyId);
+        }
+        Cipher::new(algorithm, key)?;
+
+        self.entries.push(Entry {
+            id,
+            algorithm,
+            key: key.clone(),
+        });
+        self.active = Some(self.entries.len() - 1);
+        Ok(())
+    }
+
+    /// Makes a key already in the ring the active one, e.g. to roll back a
+    /// rotation.
+    ///
+    /// # Returns
+    ///
+    /// `Error::UnknownKeyId` if no key has this id.
+    pub fn set_active(&mut self, id: &[u8]) -> Result<(), Error> {
+        let id = KeyId::new(id)?;
+        self.active = Some(self.position(&id).ok_or(Error::UnknownKeyId)?);
+        Ok(())
+    }
+
+    /// Returns the id of the key new envelopes are sealed with.
+    pub fn active_key_id(&self) -> Option<&KeyId> {
+        self.active.map(|index| &self.entries[index].id)
+    }
+
+    /// Returns whether the ring holds a key with this id.
+    pub fn contains(&self, id: &KeyId) -> bool {
+        self.position(id).is_some()
+    }
+
+    /// Encrypts the given plaintext under the active key.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The encoded envelope, which records the active key's id, or
+    /// `Error::UnknownKeyId` if the ring is empty.
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let entry = &self.entries[self.active.ok_or(Error::UnknownKeyId)?];
+        let cipher = Cipher::new(entry.algorithm, &entry.key)?;
+        Ok(Envelope::seal_with_key_id(&cipher, &entry.id, plaintext)?.to_bytes())
+    }
+
+    /// Decrypts an envelope with the key named in its header.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The encoded envelope.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext, `Error::UnknownKeyId` if the envelope names
+    /// no key or one that is not in the ring, or `Error::UnsupportedAlgorithm`
+    /// if its algorithm is not the one the key was added with.
+    pub fn decrypt(&self, blob: &[u8]) -> Result<Vec<u8>, Error> {
+        let envelope = Envelope::from_bytes(blob)?;
+        envelope.open(self.key_for(&envelope)?)
+    }
+
+    /// Re-encrypts an envelope under the active key.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The encoded envelope, sealed under any key in the ring.
+    ///
+    /// # Returns
+    ///
+    /// The new envelope. A blob already sealed under the active key is
+    /// returned unchanged.
+    pub fn reencrypt(&self, blob: &[u8]) -> Result<Vec<u8>, Error> {
+        let envelope = Envelope::from_bytes(blob)?;
+        let key = self.key_for(&envelope)?;
+        if envelope.header().key_id() == self.active_key_id() {
+            // Still authenticate it, so a corrupt blob is not passed along.
+            envelope.open(key)?;
+            return Ok(blob.to_vec());
+        }
+        self.encrypt(&envelope.open(key)?)
+    }
+
+    /// Reads the key id recorded in an envelope, without decrypting it.
+    ///
+    /// No keyring is needed, so this can route a blob to the right keyring
+    /// before any key is loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The encoded envelope.
+    ///
+    /// # Returns
+    ///
+    /// The key id, or `Error::UnknownKeyId` if the envelope carries none.
+    pub fn key_id(blob: &[u8]) -> Result<KeyId, Error> {
+        let envelope = Envelope::from_bytes(blob)?;
+        envelope
+            .header()
+            .key_id()
+            .copied()
+            .ok_or(Error::UnknownKeyId)
+    }
+
+    /// Picks the key named in the envelope header. The header's algorithm
+    /// must be the one the key was added with, so a ciphertext cannot choose
+    /// how its key is used.
+    fn key_for(&self, envelope: &Envelope) -> Result<&Key, Error> {
+        let id = envelope.header().key_id().ok_or(Error::UnknownKeyId)?;
+        let entry = &self.entries[self.position(id).ok_or(Error::UnknownKeyId)?];
+        if envelope.header().algorithm() != entry.algorithm {
+            return Err(Error::UnsupportedAlgorithm);
+        }
+        Ok(&entry.key)
+    }
+
+    fn position(&self, id: &KeyId) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == *id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope;
+
+    fn keyring() -> Keyring {
+        let mut keyring = Keyring::new();
+        keyring
+            .add(b"old", Algorithm::Aes256Gcm, &Key::new(&[1; 32]).unwrap())
+            .unwrap();
+        keyring
+            .add(
+                b"new",
+                Algorithm::ChaCha20Poly1305,
+                &Key::new(&[2; 32]).unwrap(),
+            )
+            .unwrap();
+        keyring
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_ciphertexts_readable() {
+        let mut keyring = keyring();
+        keyring.set_active(b"old").unwrap();
+        let old = keyring.encrypt(b"hello world").unwrap();
+        keyring.set_active(b"new").unwrap();
+
+        assert_eq!(Keyring::key_id(&old).unwrap().as_bytes(), b"old");
+        assert_eq!(keyring.decrypt(&old).unwrap(), b"hello world");
+
+        let migrated = keyring.reencrypt(&old).unwrap();
+        assert_eq!(Keyring::key_id(&migrated).unwrap().as_bytes(), b"new");
+        assert_eq!(
+            Envelope::from_bytes(&migrated)
+                .unwrap()
+                .header()
+                .algorithm(),
+            Algorithm::ChaCha20Poly1305
+        );
+        assert_eq!(keyring.decrypt(&migrated).unwrap(), b"hello world");
+        assert_eq!(keyring.reencrypt(&migrated).unwrap(), migrated);
+    }
+
+    #[test]
+    fn test_rejects_algorithm_other_than_the_keys() {
+        let keyring = keyring();
+        let mut blob = keyring.encrypt(b"hello world").unwrap();
+        assert_eq!(blob[1], Algorithm::ChaCha20Poly1305.id());
+
+        blob[1] = Algorithm::Aes256Gcm.id();
+        assert_eq!(keyring.decrypt(&blob), Err(Error::UnsupportedAlgorithm));
+        assert_eq!(keyring.reencrypt(&blob), Err(Error::UnsupportedAlgorithm));
+    }
+
+    #[test]
+    fn test_unknown_and_duplicate_key_ids() {
+        let mut keyring = keyring();
+        let blob = keyring.encrypt(b"hello world").unwrap();
+
+        let mut other = Keyring::new();
+        other
+            .add(b"other", Algorithm::Aes256Gcm, &Key::new(&[2; 32]).unwrap())
+            .unwrap();
+        assert_eq!(other.decrypt(&blob), Err(Error::UnknownKeyId));
+
+        let key = Key::new(&[1; 32]).unwrap();
+        let anonymous = envelope::seal(Algorithm::Aes256Gcm, &key, b"hello world").unwrap();
+        assert_eq!(keyring.decrypt(&anonymous), Err(Error::UnknownKeyId));
+
+        assert_eq!(
+            keyring.add(b"new", Algorithm::Aes256Gcm, &key),
+            Err(Error::InvalidKeyId)
+        );
+        assert_eq!(
+            keyring.add(b"", Algorithm::Aes256Gcm, &key),
+            Err(Error::InvalidKeyId)
+        );
+        assert_eq!(keyring.set_active(b"missing"), Err(Error::UnknownKeyId));
+        assert_eq!(Keyring::new().encrypt(b""), Err(Error::UnknownKeyId));
+    }
+}
@@ -0,0 +1,238 @@
+//! AES Key Wrap, for exchanging keys with HSMs and key management services.
+//!
+//! [`wrap`] and [`unwrap`] implement AES-KW (RFC 3394), which wraps key
+//! material that is a multiple of 8 bytes and at least 16 bytes long.
+//! [`wrap_with_padding`] and [`unwrap_with_padding`] implement AES-KWP
+//! (RFC 5649), which accepts any non-empty length.
+//!
+//! Both are deterministic: wrapping the same key material under the same KEK
+//! always gives the same output. That is safe for keys, which are random and
+//! wrapped once, but makes these functions unsuitable for general data; use
+//! [`envelope`](crate::envelope) for that.
+//!
+//! Requires the `keywrap` feature. The KEK length picks AES-128, AES-192 or
+//! AES-256.
+
+use alloc::{vec, vec::Vec};
+
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+use crate::key::Key;
+
+/// Length in bytes of the integrity check value each wrap adds.
+pub const OVERHEAD: usize = 8;
+
+const SEMIBLOCK_LEN: usize = 8;
+
+/// Runs `$body` with `$kek` bound to the AES-KW key for `$key`'s length.
+macro_rules! with_kek {
+    ($key:expr, |$kek:ident| $body:expr) => {{
+        let bytes = $key.as_bytes();
+        match bytes.len() {
+            16 => {
+                let $kek = KekAes128::try_from(bytes).expect("key length is checked");
+                $body
+            }
+            24 => {
+                let $kek = KekAes192::try_from(bytes).expect("key length is checked");
+                $body
+            }
+            _ => {
+                let $kek = KekAes256::try_from(bytes).expect("key length is checked");
+                $body
+            }
+        }
+    }};
+}
+
+/// Wraps key material with AES-KW (RFC 3394).
+///
+/// # Arguments
+///
+/// * `kek` - A 128, 192 or 256-bit key-encryption key.
+/// * `key_material` - The key to wrap; a multiple of 8 bytes, at least 16.
+///
+/// # Returns
+///
+/// The wrapped key, [`OVERHEAD`] bytes longer than the input, or
+/// `Error::InvalidPlaintextLength` if the input has an unsupported length.
+pub fn wrap(kek: &Key, key_material: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = key_material.len();
+    if len < 2 * SEMIBLOCK_LEN || !len.is_multiple_of(SEMIBLOCK_LEN) {
+        return Err(Error::InvalidPlaintextLength {
+            expected: len.next_multiple_of(SEMIBLOCK_LEN).max(2 * SEMIBLOCK_LEN),
+            actual: len,
+        });
+    }
+    let mut out = vec![0; len + OVERHEAD];
+    with_kek!(kek, |kek| kek.wrap(key_material, &mut out)).map_err(|_| Error::EncryptionFailed)?;
+    Ok(out)
+}
+
+/// Unwraps key material wrapped with AES-KW (RFC 3394).
+///
+/// # Arguments
+///
+/// * `kek` - The key-encryption key the material was wrapped with.
+/// * `wrapped` - The wrapped key.
+///
+/// # Returns
+///
+/// The key material, `Error::TruncatedInput` if `wrapped` cannot be an AES-KW
+/// output, or `Error::DecryptionFailed` if the integrity check fails.
+pub fn unwrap(kek: &Key, wrapped: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if wrapped.len() < 3 * SEMIBLOCK_LEN || !wrapped.len().is_multiple_of(SEMIBLOCK_LEN) {
+        return Err(Error::TruncatedInput);
+    }
+    let mut out = Zeroizing::new(vec![0; wrapped.len() - OVERHEAD]);
+    with_kek!(kek, |kek| kek.unwrap(wrapped, &mut out)).map_err(|_| Error::DecryptionFailed)?;
+    Ok(out)
+}
+
+/// Wraps key material of any length with AES-KWP (RFC 5649).
+///
+/// # Arguments
+///
+/// * `kek` - A 128, 192 or 256-bit key-encryption key.
+/// * `key_material` - The key to wrap; at least one byte.
+///
+/// # Returns
+///
+/// The wrapped key, padded to a multiple of 8 bytes plus [`OVERHEAD`], or
+/// `Error::InvalidPlaintextLength` if the input is empty or 4 GiB or longer.
+pub fn wrap_with_padding(kek: &Key, key_material: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = key_material.len();
+    if len == 0 || u32::try_from(len).is_err() {
+        return Err(Error::InvalidPlaintextLength {
+            expected: len.clamp(1, u32::MAX as usize),
+            actual: len,
+        });
+    }
+    let mut out = vec![0; len.next_multiple_of(SEMIBLOCK_LEN) + OVERHEAD];
+    with_kek!(kek, |kek| kek.wrap_with_padding(key_material, &mut out))
+        .map_err(|_| Error::EncryptionFailed)?;
+    Ok(out)
+}
+
+/// Unwraps key material wrapped with AES-KWP (RFC 5649).
+///
+/// # Arguments
+///
+/// * `kek` - The key-encryption key the material was wrapped with.
+/// * `wrapped` - The wrapped key.
+///
+/// # Returns
+///
+/// The key material with the padding removed, `Error::TruncatedInput` if
+/// `wrapped` cannot be an AES-KWP output, or `Error::DecryptionFailed` if the
+/// integrity check fails.
+pub fn unwrap_with_padding(kek: &Key, wrapped: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if wrapped.len() < 2 * SEMIBLOCK_LEN || !wrapped.len().is_multiple_of(SEMIBLOCK_LEN) {
+        return Err(Error::TruncatedInput);
+    }
+    let mut out = Zeroizing::new(vec![0; wrapped.len() - OVERHEAD]);
+    let len = with_kek!(kek, |kek| kek
+        .unwrap_with_padding(wrapped, &mut out)
+        .map(|key| key.len()))
+    .map_err(|_| Error::DecryptionFailed)?;
+    out.truncate(len);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        crate::encoding::decode_hex(text).unwrap()
+    }
+
+    #[test]
+    fn test_key_wrap_matches_rfc_3394() {
+        // RFC 3394, sections 4.1, 4.2 and 4.6.
+        let cases = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "00112233445566778899aabbccddeeff",
+                "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "00112233445566778899aabbccddeeff",
+                "96778b25ae6ca435f92b5b97c050aed2468ab8a17ad84e5d",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f",
+                "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326\
+                 cbc7f0e71a99f43bfb988b9b7a02dd21",
+            ),
+        ];
+        for (kek, material, expected) in cases {
+            let kek = Key::new(&hex(kek)).unwrap();
+            let wrapped = wrap(&kek, &hex(material)).unwrap();
+            assert_eq!(wrapped, hex(expected));
+            assert_eq!(*unwrap(&kek, &wrapped).unwrap(), hex(material));
+
+            let mut tampered = wrapped.clone();
+            tampered[0] ^= 1;
+            assert_eq!(unwrap(&kek, &tampered), Err(Error::DecryptionFailed));
+        }
+
+        let kek = Key::new(&[0; 16]).unwrap();
+        assert_eq!(
+            wrap(&kek, &[0; 12]),
+            Err(Error::InvalidPlaintextLength {
+                expected: 16,
+                actual: 12
+            })
+        );
+        assert_eq!(unwrap(&kek, &[0; 16]), Err(Error::TruncatedInput));
+        assert_eq!(unwrap(&kek, &[0; 25]), Err(Error::TruncatedInput));
+    }
+
+    #[test]
+    fn test_key_wrap_with_padding_matches_rfc_5649() {
+        // RFC 5649, section 6.
+        let kek = Key::new(&hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8")).unwrap();
+        let cases = [
+            (
+                "c37b7e6492584340bed12207808941155068f738",
+                "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+            ),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ];
+        for (material, expected) in cases {
+            let wrapped = wrap_with_padding(&kek, &hex(material)).unwrap();
+            assert_eq!(wrapped, hex(expected));
+            assert_eq!(*unwrap_with_padding(&kek, &wrapped).unwrap(), hex(material));
+
+            let mut tampered = wrapped.clone();
+            tampered[wrapped.len() - 1] ^= 1;
+            assert_eq!(
+                unwrap_with_padding(&kek, &tampered),
+                Err(Error::DecryptionFailed)
+            );
+        }
+
+        // An AES-KW output is not a valid AES-KWP output.
+        let wrapped = wrap(&kek, &[7; 16]).unwrap();
+        assert_eq!(
+            unwrap_with_padding(&kek, &wrapped),
+            Err(Error::DecryptionFailed)
+        );
+        assert_eq!(
+            unwrap_with_padding(&kek, &[0; 8]),
+            Err(Error::TruncatedInput)
+        );
+        assert_eq!(
+            wrap_with_padding(&kek, &[]),
+            Err(Error::InvalidPlaintextLength {
+                expected: 1,
+                actual: 0
+            })
+        );
+    }
+}
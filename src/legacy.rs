@@ -0,0 +1,265 @@
+//! AES-CBC and AES-CTR, for reading data written by older systems.
+//!
+//! **These modes are not authenticated.** A modified ciphertext decrypts to
+//! modified plaintext without any error, and CBC decryption that reports
+//! padding failures to an attacker leaks the plaintext (a padding oracle).
+//! Only use [`cbc_decrypt`] and [`ctr_decrypt`] to migrate trusted legacy
+//! data, and never expose their errors to untrusted callers.
+//!
+//! [`seal_etm`] and [`open_etm`] wrap AES-CBC in encrypt-then-MAC with
+//! HMAC-SHA256, for systems that cannot move to an AEAD envelope yet:
+//!
+//! ```text
+//! offset  size  field
+//! 0       16    IV
+//! 16      ..    AES-CBC ciphertext, PKCS#7 padded
+//! ..      32    HMAC-SHA256 over the IV and ciphertext
+//! ```
+//!
+//! The encryption and MAC keys are derived from one key with HKDF-SHA256.
+//! New data should still use [`envelope`](crate::envelope).
+//!
+//! Requires the `legacy` feature. The key length picks AES-128, AES-192 or
+//! AES-256.
+
+use alloc::vec::Vec;
+
+use aes::cipher::{
+    block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit, StreamCipher,
+};
+use ring::hmac;
+
+use crate::error::Error;
+use crate::kdf;
+use crate::key::Key;
+use crate::rng;
+
+/// Length in bytes of the AES block and of the CBC/CTR IV.
+pub const BLOCK_LEN: usize = 16;
+
+/// Length in bytes of the HMAC-SHA256 tag appended by [`seal_etm`].
+pub const MAC_LEN: usize = 32;
+
+const ETM_ENCRYPTION_INFO: &[u8] = b"aes_implementation/legacy/etm/encryption";
+const ETM_MAC_INFO: &[u8] = b"aes_implementation/legacy/etm/mac";
+
+/// Encrypts with AES-CBC and PKCS#7 padding.
+///
+/// # Arguments
+///
+/// * `key` - A 128, 192 or 256-bit key.
+/// * `iv` - The IV; must be unpredictable and never reused under the key.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The ciphertext, padded to a whole number of blocks.
+pub fn cbc_encrypt(key: &Key, iv: &[u8; BLOCK_LEN], plaintext: &[u8]) -> Vec<u8> {
+    fn encrypt<C: KeyIvInit + BlockEncryptMut>(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        C::new_from_slices(key, iv)
+            .expect("key and IV lengths are checked")
+            .encrypt_padded_vec_mut::<Pkcs7>(plaintext)
+    }
+
+    let key = key.as_bytes();
+    match key.len() {
+        16 => encrypt::<cbc::Encryptor<aes::Aes128>>(key, iv, plaintext),
+        24 => encrypt::<cbc::Encryptor<aes::Aes192>>(key, iv, plaintext),
+        _ => encrypt::<cbc::Encryptor<aes::Aes256>>(key, iv, plaintext),
+    }
+}
+
+/// Decrypts AES-CBC with PKCS#7 padding, e.g. Java's
+/// `AES/CBC/PKCS5Padding`.
+///
+/// # Arguments
+///
+/// * `key` - The key the data was encrypted with.
+/// * `iv` - The IV the data was encrypted with.
+/// * `ciphertext` - The data to decrypt.
+///
+/// # Returns
+///
+/// The plaintext, `Error::TruncatedInput` if the ciphertext is not a whole
+/// number of blocks, or `Error::DecryptionFailed` if the padding is invalid.
+/// A wrong key usually, but not always, shows up as invalid padding.
+pub fn cbc_decrypt(key: &Key, iv: &[u8; BLOCK_LEN], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+    fn decrypt<C: KeyIvInit + BlockDecryptMut>(
+        key: &[u8],
+        iv: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        C::new_from_slices(key, iv)
+            .expect("key and IV lengths are checked")
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .map_err(|_| Error::DecryptionFailed)
+    }
+
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(BLOCK_LEN) {
+        return Err(Error::TruncatedInput);
+    }
+    let key = key.as_bytes();
+    match key.len() {
+        16 => decrypt::<cbc::Decryptor<aes::Aes128>>(key, iv, ciphertext),
+        24 => decrypt::<cbc::Decryptor<aes::Aes192>>(key, iv, ciphertext),
+        _ => decrypt::<cbc::Decryptor<aes::Aes256>>(key, iv, ciphertext),
+    }
+}
+
+/// Encrypts with AES-CTR, using a 128-bit big-endian counter that starts at
+/// `iv`, as Java's `AES/CTR/NoPadding` does.
+///
+/// # Arguments
+///
+/// * `key` - A 128, 192 or 256-bit key.
+/// * `iv` - The initial counter block; never reuse it under the key.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The ciphertext, as long as the plaintext.
+pub fn ctr_encrypt(key: &Key, iv: &[u8; BLOCK_LEN], plaintext: &[u8]) -> Vec<u8> {
+    fn apply<C: KeyIvInit + StreamCipher>(key: &[u8], iv: &[u8], data: &mut [u8]) {
+        C::new_from_slices(key, iv)
+            .expect("key and IV lengths are checked")
+            .apply_keystream(data);
+    }
+
+    let mut data = plaintext.to_vec();
+    let key = key.as_bytes();
+    match key.len() {
+        16 => apply::<ctr::Ctr128BE<aes::Aes128>>(key, iv, &mut data),
+        24 => apply::<ctr::Ctr128BE<aes::Aes192>>(key, iv, &mut data),
+        _ => apply::<ctr::Ctr128BE<aes::Aes256>>(key, iv, &mut data),
+    }
+    data
+}
+
+/// Decrypts AES-CTR; the same operation as [`ctr_encrypt`].
+///
+/// Any ciphertext decrypts: a wrong key or modified data is not detected.
+pub fn ctr_decrypt(key: &Key, iv: &[u8; BLOCK_LEN], ciphertext: &[u8]) -> Vec<u8> {
+    ctr_encrypt(key, iv, ciphertext)
+}
+
+/// Encrypts with AES-CBC under a random IV and appends an HMAC-SHA256 tag.
+///
+/// # Arguments
+///
+/// * `key` - A 128, 192 or 256-bit key; the encryption and MAC keys are
+///   derived from it.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The blob laid out as in the module documentation.
+pub fn seal_etm(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let (encryption_key, mac_key) = etm_keys(key)?;
+
+    let mut iv = [0u8; BLOCK_LEN];
+    rng::fill(&mut iv)?;
+    let mut blob = iv.to_vec();
+    blob.extend_from_slice(&cbc_encrypt(&encryption_key, &iv, plaintext));
+
+    let tag = hmac::sign(&mac_key, &blob);
+    blob.extend_from_slice(tag.as_ref());
+    Ok(blob)
+}
+
+/// Verifies and decrypts a blob produced by [`seal_etm`].
+///
+/// The tag is checked in constant time before anything is decrypted, so
+/// there is no padding oracle.
+///
+/// # Returns
+///
+/// The plaintext, `Error::TruncatedInput` if the blob is too short, or
+/// `Error::DecryptionFailed` if the key is wrong or the blob was modified.
+pub fn open_etm(key: &Key, blob: &[u8]) -> Result<Vec<u8>, Error> {
+    let (encryption_key, mac_key) = etm_keys(key)?;
+    if blob.len() < 2 * BLOCK_LEN + MAC_LEN {
+        return Err(Error::TruncatedInput);
+    }
+
+    let (authenticated, tag) = blob.split_at(blob.len() - MAC_LEN);
+    hmac::verify(&mac_key, authenticated, tag).map_err(|_| Error::DecryptionFailed)?;
+
+    let (iv, ciphertext) = authenticated.split_at(BLOCK_LEN);
+    cbc_decrypt(&encryption_key, iv.try_into().unwrap(), ciphertext)
+}
+
+fn etm_keys(key: &Key) -> Result<(Key, hmac::Key), Error> {
+    let encryption_key = kdf::derive_key(key, b"", ETM_ENCRYPTION_INFO)?;
+    let mac_key = kdf::derive_key(key, b"", ETM_MAC_INFO)?;
+    Ok((
+        encryption_key,
+        hmac::Key::new(hmac::HMAC_SHA256, mac_key.as_bytes()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        crate::encoding::decode_hex(text).unwrap()
+    }
+
+    // NIST SP 800-38A, appendix F: the AES-256 key and four plaintext blocks.
+    const KEY: &str = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_cbc_matches_sp_800_38a() {
+        let key = Key::new(&hex(KEY)).unwrap();
+        let iv: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let expected = hex(
+            "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d\
+             39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b",
+        );
+
+        // PKCS#7 adds a full block of padding to block-aligned input.
+        let ciphertext = cbc_encrypt(&key, &iv, &hex(PLAINTEXT));
+        assert_eq!(ciphertext.len(), expected.len() + BLOCK_LEN);
+        assert_eq!(ciphertext[..expected.len()], expected);
+        assert_eq!(cbc_decrypt(&key, &iv, &ciphertext).unwrap(), hex(PLAINTEXT));
+
+        assert_eq!(
+            cbc_decrypt(&key, &iv, &ciphertext[1..]),
+            Err(Error::TruncatedInput)
+        );
+        assert_eq!(
+            cbc_decrypt(&key, &iv, &expected),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_ctr_matches_sp_800_38a() {
+        let key = Key::new(&hex(KEY)).unwrap();
+        let iv: [u8; 16] = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").try_into().unwrap();
+        let expected = hex(
+            "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+             2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6",
+        );
+
+        assert_eq!(ctr_encrypt(&key, &iv, &hex(PLAINTEXT)), expected);
+        assert_eq!(ctr_decrypt(&key, &iv, &expected), hex(PLAINTEXT));
+    }
+
+    #[test]
+    fn test_encrypt_then_mac() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let blob = seal_etm(&key, b"hello world").unwrap();
+        assert_eq!(blob.len(), 2 * BLOCK_LEN + MAC_LEN);
+        assert_eq!(open_etm(&key, &blob).unwrap(), b"hello world");
+
+        for index in [0, BLOCK_LEN, blob.len() - 1] {
+            let mut tampered = blob.clone();
+            tampered[index] ^= 1;
+            assert_eq!(open_etm(&key, &tampered), Err(Error::DecryptionFailed));
+        }
+        assert_eq!(open_etm(&key, &blob[..40]), Err(Error::TruncatedInput));
+    }
+}
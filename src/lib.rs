@@ -1,164 +1,103 @@
-extern crate ring;
-
-use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
-use ring::error::Unspecified;
-use ring::rand::{SecureRandom, SystemRandom};
-
-/// Encrypts the given plaintext using AES-256-GCM encryption.
-///
-/// # Arguments
-///
-/// * `key` - A 32-byte key for AES-256-GCM encryption.
-/// * `plaintext` - The data to encrypt.
-///
-/// # Returns
-///
-/// A tuple containing the encrypted ciphertext and the nonce used for encryption.
-fn main() {
-    pub fn encrypt_aes_256_gcm(
-        key: &[u8],
-        plaintext: &[u8],
-    ) -> Result<(Vec<u8>, Vec<u8>), Unspecified> {
-        // Ensure the key length is 32 bytes for AES-256
-        assert_eq!(key.len(), 32);
-
-        // Generate a random nonce
-        let mut nonce = vec![12; NONCE_LEN];
-        SystemRandom::new().fill(&mut nonce)?;
-
-        let nonce = Nonce::assume_unique_for_key([12; NONCE_LEN]);
-        let aad = Aad::empty();
-        let mut in_out = plaintext.to_vec();
-
-        // Initialize the key and encrypt the data
-        let key = UnboundKey::new(&AES_256_GCM, key)?;
-        let key = LessSafeKey::new(key);
-        key.seal_in_place_append_tag(nonce, aad, &mut in_out)?;
-
-        Ok((in_out, nonce.as_ref().to_vec()))
-    }
-
-    /// Decrypts the given ciphertext using AES-256-GCM encryption.
-    ///
-    /// # Arguments
-    ///
-    /// * `key` - A 32-byte key for AES-256-GCM decryption.
-    /// * `nonce` - The nonce used for encryption.
-    /// * `ciphertext` - The data to decrypt.
-    ///
-    /// # Returns
-    ///
-    /// The decrypted plaintext.
-    pub fn decrypt_aes_256_gcm(
-        key: &[u8],
-        nonce: &[u8],
-        ciphertext: &[u8],
-    ) -> Result<Vec<u8>, Unspecified> {
-        // Ensure the key length is 32 bytes for AES-256
-        assert_eq!(key.len(), 32);
-
-        let nonce = Nonce::try_assume_unique_for_key(nonce)?;
-        let aad = Aad::empty();
-        let mut in_out = ciphertext.to_vec();
-
-        // Initialize the key and decrypt the data
-        let key = UnboundKey::new(&AES_256_GCM, key)?;
-        let key = LessSafeKey::new(key);
-        key.open_in_place(nonce, aad, &mut in_out)?;
-
-        // Extract the plaintext from the decrypted data
-        let plaintext = in_out.split_off(in_out.len() - AES_256_GCM.tag_len());
-        Ok(plaintext)
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        pub fn encrypt_aes_256_gcm(
-            key: &[u8],
-            plaintext: &[u8],
-        ) -> Result<(Vec<u8>, Vec<u8>), Unspecified> {
-            // Ensure the key length is 32 bytes for AES-256
-            assert_eq!(key.len(), 32);
-
-            // Generate a random nonce
-            let mut nonce = vec![12; NONCE_LEN];
-            SystemRandom::new().fill(&mut nonce)?;
-
-            let nonce = Nonce::assume_unique_for_key([12; NONCE_LEN]);
-            let aad = Aad::empty();
-            let mut in_out = plaintext.to_vec();
-
-            // Initialize the key and encrypt the data
-            let key = UnboundKey::new(&AES_256_GCM, key)?;
-            let key = LessSafeKey::new(key);
-            key.seal_in_place_append_tag(nonce, aad, &mut in_out)?;
-
-            Ok((in_out, nonce.as_ref().to_vec()))
-        }
-
-        /// Decrypts the given ciphertext using AES-256-GCM encryption.
-        ///
-        /// # Arguments
-        ///
-        /// * `key` - A 32-byte key for AES-256-GCM decryption.
-        /// * `nonce` - The nonce used for encryption.
-        /// * `ciphertext` - The data to decrypt.
-        ///
-        /// # Returns
-        ///
-        /// The decrypted plaintext.
-        pub fn decrypt_aes_256_gcm(
-            key: &[u8],
-            nonce: &[u8],
-            ciphertext: &[u8],
-        ) -> Result<Vec<u8>, Unspecified> {
-            // Ensure the key length is 32 bytes for AES-256
-            assert_eq!(key.len(), 32);
-
-            let nonce = Nonce::try_assume_unique_for_key(nonce)?;
-            let aad = Aad::empty();
-            let mut in_out = ciphertext.to_vec();
-
-            // Initialize the key and decrypt the data
-            let key = UnboundKey::new(&AES_256_GCM, key)?;
-            let key = LessSafeKey::new(key);
-            key.open_in_place(nonce, aad, &mut in_out)?;
-
-            // Extract the plaintext from the decrypted data
-            let plaintext = in_out.split_off(in_out.len() - AES_256_GCM.tag_len());
-            Ok(plaintext)
-        }
-
-        #[cfg(test)]
-        mod tests {
-            use super::*;
-
-            #[test]
-            fn test_aes_256_gcm_encryption_decryption() {
-                let key = b"an example very very secret key."; // 32 bytes
-                let plaintext = b"hello world";
-
-                let (ciphertext, nonce) =
-                    encrypt_aes_256_gcm(key, plaintext).expect("encryption failed");
-                let decrypted_plaintext =
-                    decrypt_aes_256_gcm(key, &nonce, &ciphertext).expect("decryption failed");
-
-                assert_eq!(plaintext.to_vec(), decrypted_plaintext);
-            }
-        }
-
-        #[test]
-        fn test_aes_256_gcm_encryption_decryption() {
-            let key = b"an example very very secret key."; // 32 bytes
-            let plaintext = b"hello world";
-
-            let (ciphertext, nonce) =
-                encrypt_aes_256_gcm(key, plaintext).expect("encryption failed");
-            let decrypted_plaintext =
-                decrypt_aes_256_gcm(key, &nonce, &ciphertext).expect("decryption failed");
-
-            assert_eq!(plaintext.to_vec(), decrypted_plaintext);
-        }
-    }
-}
+//! AES-GCM and ChaCha20-Poly1305 authenticated encryption built on `ring`.
+//!
+//! With the default `std` feature disabled the crate is `no_std` and only
+//! needs `alloc`. File and stream I/O, batch decryption, the object store
+//! and the thread-backed services are then unavailable.
+//!
+//! # Randomness without `std`
+//!
+//! Decryption never needs randomness, and neither do [`DeterministicCipher`],
+//! key wrapping or the ring buffer's `push`. Everything else that seals
+//! draws from the operating system RNG through ring's `SystemRandom`.
+//! Without `std`, ring reaches it through `getrandom`.
+//! So on a target without OS entropy, the following fail at runtime with
+//! `Error::RandomFailed` unless a custom `getrandom` backend is registered:
+//!
+//! * [`Key::generate`], [`Key::generate_for`],
+//!   [`VerificationKey::generate`](verifiable::VerificationKey::generate)
+//!   and [`NonceSequence::random`](nonce::NonceSequence::random).
+//! * [`Cipher::encrypt`], [`Cipher::encrypt_with_aad`],
+//!   [`Cipher::encrypt_vectored`], [`Cipher::seal_in_place`] and the
+//!   `encrypt_aes_256_gcm*` functions, which use
+//!   [`RandomNonce`](nonce::RandomNonce).
+//! * [`Envelope::seal`], [`Envelope::seal_with_key_id`],
+//!   [`Envelope::seal_with_subkey`], [`Envelope::seal_cascade`] and the
+//!   [`envelope`] functions [`seal`], [`seal_with_subkey`], [`seal_cascade`],
+//!   [`seal_wrapped`], [`seal_vectored`], `seal_in_place`, `seal_to_base64`
+//!   and `seal_to_hex`.
+//! * The APIs built on envelopes: [`Keyring::encrypt`],
+//!   [`Keyring::reencrypt`], [`RecordCipher::seal`], [`protect`],
+//!   [`encrypt_with_password`], `encrypt_with_password_params` and
+//!   [`verifiable::seal`].
+//! * [`ece::encrypt`], [`ceremony::generate`], [`ceremony::split`],
+//!   `EncryptedRingBuffer::create` and, with the `legacy` feature,
+//!   `legacy::seal_etm`.
+//!
+//! To seal with a generator of your own instead, pass a
+//! [`RngNonce`](nonce::RngNonce) or another [`NonceStrategy`](nonce::NonceStrategy)
+//! to [`Envelope::seal_with_strategy`] or [`Cipher::encrypt_with_strategy`].
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub mod aead;
+#[cfg(feature = "tokio")]
+pub mod async_stream;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod ceremony;
+pub mod deterministic;
+pub mod ece;
+pub mod encoding;
+pub mod envelope;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod fs;
+pub mod kdf;
+pub mod key;
+pub mod keyring;
+#[cfg(feature = "keywrap")]
+pub mod keywrap;
+#[cfg(feature = "legacy")]
+pub mod legacy;
+pub mod nonce;
+pub mod password;
+pub mod record;
+#[cfg(target_has_atomic = "64")]
+pub mod ring_buffer;
+mod rng;
+pub mod secret;
+#[cfg(feature = "tokio")]
+pub mod service;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "vectors")]
+pub mod vectors;
+pub mod verifiable;
+
+pub use aead::{
+    decrypt_aes_256_gcm, decrypt_aes_256_gcm_with_aad, encrypt_aes_256_gcm,
+    encrypt_aes_256_gcm_with_aad, Algorithm, Cipher, Tag,
+};
+#[cfg(feature = "tokio")]
+pub use async_stream::{AsyncStreamDecryptor, AsyncStreamEncryptor};
+#[cfg(feature = "std")]
+pub use batch::{decrypt_batch, decrypt_batch_with_keyring};
+pub use deterministic::DeterministicCipher;
+pub use encoding::Base64Alphabet;
+pub use envelope::{
+    open, open_wrapped, seal, seal_cascade, seal_vectored, seal_with_subkey, seal_wrapped, Envelope,
+};
+pub use error::{Contextual, Error, ErrorKind};
+pub use key::Key;
+pub use keyring::Keyring;
+pub use password::{decrypt_with_password, encrypt_with_password};
+pub use record::RecordCipher;
+pub use secret::{protect, reveal};
+#[cfg(feature = "tokio")]
+pub use service::EncryptionService;
+#[cfg(feature = "std")]
+pub use stream::{StreamDecryptor, StreamEncryptor};
@@ -0,0 +1,314 @@
+//! Nonce generation policies.
+//!
+//! A nonce must never repeat under the same key. [`NonceStrategy`] makes the
+//! policy explicit at the call site of [`Cipher::encrypt_with_strategy`]:
+//!
+//! * [`RandomNonce`] draws 96 random bits per message. This is what
+//!   [`Cipher::encrypt`] uses; keep the number of messages per key well below
+//!   2^32 to stay within the birthday bound.
+//! * [`RngNonce`] does the same with a random number generator supplied by
+//!   the caller, for `no_std` targets.
+//! * [`NonceSequence`] counts up from a fixed prefix and never repeats, as
+//!   long as one sequence is the only user of its key and prefix.
+//! * [`FixedNonce`] passes through a nonce chosen by the caller, e.g. one
+//!   mandated by a wire protocol, and refuses to hand it out twice.
+//! * [`XorNonce`] XORs a static IV with a 64-bit record sequence number, as
+//!   TLS 1.3, DTLS 1.3 and QUIC do.
+//!
+//! [`Cipher::encrypt_with_strategy`]: crate::aead::Cipher::encrypt_with_strategy
+//! [`Cipher::encrypt`]: crate::aead::Cipher::encrypt
+
+use zeroize::Zeroizing;
+
+use crate::aead::{Algorithm, NONCE_LEN};
+use crate::error::Error;
+use crate::kdf;
+use crate::key::Key;
+use crate::rng;
+
+/// A source of nonces for successive encryptions under one key.
+pub trait NonceStrategy {
+    /// Returns the nonce for the next message, or `Error::NonceExhausted` if
+    /// the strategy cannot produce another unique nonce.
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error>;
+}
+
+/// A fresh random nonce for every message.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomNonce;
+
+impl NonceStrategy for RandomNonce {
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rng::fill(&mut nonce)?;
+        Ok(nonce)
+    }
+}
+
+/// Counter-based nonces: a 4-byte fixed prefix followed by a 64-bit
+/// big-endian message counter.
+#[derive(Debug, Clone)]
+pub struct NonceSequence {
+    prefix: [u8; 4],
+    counter: Option<u64>,
+}
+
+impl NonceSequence {
+    /// Creates a sequence that starts at counter zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - A value unique to this sequence among all users of the
+    ///   key, e.g. a sender id.
+    pub fn new(prefix: [u8; 4]) -> NonceSequence {
+        NonceSequence {
+            prefix,
+            counter: Some(0),
+        }
+    }
+
+    /// Creates a sequence with a random prefix.
+    pub fn random() -> Result<NonceSequence, Error> {
+        let mut prefix = [0u8; 4];
+        rng::fill(&mut prefix)?;
+        Ok(NonceSequence::new(prefix))
+    }
+}
+
+impl NonceStrategy for NonceSequence {
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        let counter = self.counter.ok_or(Error::NonceExhausted)?;
+        self.counter = counter.checked_add(1);
+
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..4].copy_from_slice(&self.prefix);
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        Ok(nonce)
+    }
+}
+
+/// A caller-supplied nonce, handed out at most once.
+#[derive(Debug, Clone)]
+pub struct FixedNonce(Option<[u8; NONCE_LEN]>);
+
+impl FixedNonce {
+    /// Wraps a nonce chosen by the caller, who is responsible for never
+    /// supplying the same nonce twice under one key.
+    pub fn new(nonce: [u8; NONCE_LEN]) -> FixedNonce {
+        FixedNonce(Some(nonce))
+    }
+}
+
+impl NonceStrategy for FixedNonce {
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        self.0.take().ok_or(Error::NonceExhausted)
+    }
+}
+
+/// Random nonces from a caller-supplied generator, for targets where the
+/// operating system RNG behind [`RandomNonce`] is unavailable.
+pub struct RngNonce<F> {
+    fill: F,
+}
+
+impl<F: FnMut(&mut [u8]) -> Result<(), Error>> RngNonce<F> {
+    /// Wraps a random number generator.
+    ///
+    /// # Arguments
+    ///
+    /// * `fill` - Fills its argument with cryptographically secure random
+    ///   bytes, or returns an error such as `Error::RandomFailed`.
+    pub fn new(fill: F) -> RngNonce<F> {
+        RngNonce { fill }
+    }
+}
+
+impl<F: FnMut(&mut [u8]) -> Result<(), Error>> NonceStrategy for RngNonce<F> {
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        let mut nonce = [0u8; NONCE_LEN];
+        (self.fill)(&mut nonce)?;
+        Ok(nonce)
+    }
+}
+
+/// Record nonces in the style of TLS 1.3 (RFC 8446, section 5.3): a static
+/// per-connection IV XORed with the 64-bit record sequence number, left-padded
+/// with zeros to [`NONCE_LEN`] bytes.
+///
+/// Senders draw nonces with [`NonceStrategy::next_nonce`], which numbers
+/// records from zero. Receivers that learn the sequence number from the
+/// record, as in DTLS and QUIC, call [`XorNonce::nonce_for`] instead.
+#[derive(Debug, Clone)]
+pub struct XorNonce {
+    iv: [u8; NONCE_LEN],
+    sequence: Option<u64>,
+}
+
+impl XorNonce {
+    /// Creates a nonce sequence from a static IV, starting at record zero.
+    pub fn new(iv: [u8; NONCE_LEN]) -> XorNonce {
+        XorNonce {
+            iv,
+            sequence: Some(0),
+        }
+    }
+
+    /// Derives the record key and IV from a TLS 1.3 traffic secret with
+    /// HKDF-Expand-Label, for the SHA-256 cipher suites.
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The record protection algorithm, which sets the key
+    ///   length.
+    /// * `secret` - The 32-byte traffic secret.
+    ///
+    /// # Returns
+    ///
+    /// The record key and nonce sequence, or `Error::InvalidKeyLength` if the
+    /// secret is not 32 bytes.
+    pub fn from_traffic_secret(
+        algorithm: Algorithm,
+        secret: &[u8],
+    ) -> Result<(Key, XorNonce), Error> {
+        XorNonce::derive(algorithm, secret, b"key", b"iv")
+    }
+
+    /// Like [`XorNonce::from_traffic_secret`], but with the `quic key` and
+    /// `quic iv` labels of QUIC packet protection (RFC 9001, section 5.1).
+    pub fn from_quic_secret(algorithm: Algorithm, secret: &[u8]) -> Result<(Key, XorNonce), Error> {
+        XorNonce::derive(algorithm, secret, b"quic key", b"quic iv")
+    }
+
+    fn derive(
+        algorithm: Algorithm,
+        secret: &[u8],
+        key_label: &[u8],
+        iv_label: &[u8],
+    ) -> Result<(Key, XorNonce), Error> {
+        if secret.len() != 32 {
+            return Err(Error::InvalidKeyLength {
+                expected: 32,
+                actual: secret.len(),
+            });
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        let key = &mut key[..algorithm.key_len()];
+        kdf::expand_label(secret, key_label, &[], key)?;
+        let mut iv = [0u8; NONCE_LEN];
+        kdf::expand_label(secret, iv_label, &[], &mut iv)?;
+
+        Ok((Key::for_algorithm(algorithm, key)?, XorNonce::new(iv)))
+    }
+
+    /// Returns the nonce of the record with the given sequence number.
+    pub fn nonce_for(&self, sequence: u64) -> [u8; NONCE_LEN] {
+        let mut nonce = self.iv;
+        for (byte, seq) in nonce[NONCE_LEN - 8..]
+            .iter_mut()
+            .zip(sequence.to_be_bytes())
+        {
+            *byte ^= seq;
+        }
+        nonce
+    }
+}
+
+impl NonceStrategy for XorNonce {
+    fn next_nonce(&mut self) -> Result<[u8; NONCE_LEN], Error> {
+        let sequence = self.sequence.ok_or(Error::NonceExhausted)?;
+        self.sequence = sequence.checked_add(1);
+        Ok(self.nonce_for(sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_nonce_sequence_never_repeats() {
+        let mut nonces = NonceSequence::new(*b"abcd");
+        let seen: HashSet<_> = (0..10_000).map(|_| nonces.next_nonce().unwrap()).collect();
+        assert_eq!(seen.len(), 10_000);
+        assert!(seen.iter().all(|nonce| nonce.starts_with(b"abcd")));
+
+        nonces.counter = Some(u64::MAX);
+        let last = nonces.next_nonce().unwrap();
+        assert_eq!(last[4..], [0xff; 8]);
+        assert_eq!(nonces.next_nonce(), Err(Error::NonceExhausted));
+    }
+
+    #[test]
+    fn test_fixed_nonce_is_used_once() {
+        let mut nonce = FixedNonce::new([7; NONCE_LEN]);
+        assert_eq!(nonce.next_nonce(), Ok([7; NONCE_LEN]));
+        assert_eq!(nonce.next_nonce(), Err(Error::NonceExhausted));
+    }
+
+    #[test]
+    fn test_rng_nonce_uses_caller_generator() {
+        let mut counter = 0u8;
+        let mut nonces = RngNonce::new(|out: &mut [u8]| {
+            counter += 1;
+            out.fill(counter);
+            Ok(())
+        });
+        assert_eq!(nonces.next_nonce(), Ok([1; NONCE_LEN]));
+        assert_eq!(nonces.next_nonce(), Ok([2; NONCE_LEN]));
+
+        let mut failing = RngNonce::new(|_: &mut [u8]| Err(Error::RandomFailed));
+        assert_eq!(failing.next_nonce(), Err(Error::RandomFailed));
+    }
+
+    #[test]
+    fn test_xor_nonce_matches_rfc_8448() {
+        // RFC 8448, section 3: the server handshake traffic secret of the
+        // simple 1-RTT handshake, with TLS_AES_128_GCM_SHA256.
+        let secret = crate::encoding::decode_hex(
+            "b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38",
+        )
+        .unwrap();
+        let (key, mut nonces) =
+            XorNonce::from_traffic_secret(Algorithm::Aes128Gcm, &secret).unwrap();
+        assert_eq!(
+            crate::encoding::encode_hex(key.as_bytes()),
+            "3fce516009c21727d0f2e4e86ee403bc"
+        );
+
+        let iv = crate::encoding::decode_hex("5d313eb2671276ee13000b30").unwrap();
+        assert_eq!(nonces.next_nonce().unwrap()[..], iv[..]);
+        let second = nonces.next_nonce().unwrap();
+        assert_eq!(second[..11], iv[..11]);
+        assert_eq!(second[11], iv[11] ^ 1);
+        assert_eq!(nonces.nonce_for(1), second);
+
+        nonces.sequence = Some(u64::MAX);
+        nonces.next_nonce().unwrap();
+        assert_eq!(nonces.next_nonce(), Err(Error::NonceExhausted));
+
+        assert_eq!(
+            XorNonce::from_traffic_secret(Algorithm::Aes128Gcm, &secret[1..]).err(),
+            Some(Error::InvalidKeyLength {
+                expected: 32,
+                actual: 31
+            })
+        );
+
+        // RFC 9001, appendix A.1: the client Initial secret.
+        let secret = crate::encoding::decode_hex(
+            "c00cf151ca5be075ed0ebfb5c80323c42d6b7db67881289af4008f1f6c357aea",
+        )
+        .unwrap();
+        let (key, nonces) = XorNonce::from_quic_secret(Algorithm::Aes128Gcm, &secret).unwrap();
+        assert_eq!(
+            crate::encoding::encode_hex(key.as_bytes()),
+            "1f369613dd76d5467730efcbe3b1a22d"
+        );
+        assert_eq!(
+            crate::encoding::encode_hex(&nonces.nonce_for(0)),
+            "fa044b2f42a3fd3b46fb255c"
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Password-based encryption.
+//!
+//! The key is derived from the password with PBKDF2-HMAC-SHA256, or Argon2id
+//! with the `argon2` feature, and the payload is sealed into an AES-256-GCM
+//! envelope. The derivation parameters and salt are stored in front of the
+//! envelope so that only the password is needed to decrypt:
+//!
+//! ```text
+//! offset  size  field
+//! 0       1     version (currently 1)
+//! 1       1     KDF identifier (1 = PBKDF2-HMAC-SHA256, 2 = Argon2id)
+//! 2       12    KDF parameters, three big-endian u32 (unused ones are zero)
+//! 14      16    salt
+//! 30      ..    envelope
+//! ```
+//!
+//! Parameters read back from a blob are checked against fixed bounds before
+//! any key derivation runs, so a crafted blob can neither downgrade the work
+//! factor nor make decryption hang.
+
+use alloc::vec::Vec;
+use core::num::NonZeroU32;
+
+use ring::pbkdf2;
+use zeroize::Zeroizing;
+
+use crate::aead::Algorithm;
+use crate::envelope;
+use crate::error::Error;
+use crate::key::{Key, KEY_LEN};
+use crate::rng;
+
+/// The password blob format version written by this crate.
+pub const VERSION: u8 = 1;
+
+/// Length in bytes of the random salt.
+pub const SALT_LEN: usize = 16;
+
+/// Length in bytes of the header in front of the envelope.
+pub const HEADER_LEN: usize = 2 + 12 + SALT_LEN;
+
+/// PBKDF2 iteration count used by [`encrypt_with_password`].
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Fewest PBKDF2 iterations accepted when encrypting or decrypting.
+pub const MIN_PBKDF2_ITERATIONS: u32 = 10_000;
+
+/// Most PBKDF2 iterations accepted when decrypting.
+pub const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+// Argon2id bounds: between 8 MiB and 1 GiB of memory, at most 16 lanes and
+// 64 passes.
+const MIN_ARGON2_MEMORY_KIB: u32 = 8 * 1024;
+const MAX_ARGON2_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+/// The key derivation function and its cost parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfParams {
+    /// PBKDF2 with HMAC-SHA256.
+    Pbkdf2Sha256 { iterations: u32 },
+    /// Argon2id, version 0x13. Requires the `argon2` feature.
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::Pbkdf2Sha256 {
+            iterations: DEFAULT_PBKDF2_ITERATIONS,
+        }
+    }
+}
+
+impl KdfParams {
+    /// Argon2id parameters recommended by RFC 9106 for memory-constrained
+    /// environments: 64 MiB, 3 passes, 4 lanes.
+    pub fn argon2id() -> Self {
+        KdfParams::Argon2id {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+
+    /// Derives a 256-bit key from the password.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password.
+    /// * `salt` - A random salt, unique per encryption.
+    ///
+    /// # Returns
+    ///
+    /// The derived key, or `Error::InvalidKdfParams` if the parameters fall
+    /// outside the accepted bounds.
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<Key, Error> {
+        self.validate()?;
+
+        let mut out = Zeroizing::new([0u8; KEY_LEN]);
+        match *self {
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                let iterations = NonZeroU32::new(iterations).ok_or(Error::InvalidKdfParams)?;
+                pbkdf2::derive(
+                    pbkdf2::PBKDF2_HMAC_SHA256,
+                    iterations,
+                    salt,
+                    password,
+                    &mut out[..],
+                );
+            }
+            #[cfg(feature = "argon2")]
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params =
+                    argon2::Params::new(memory_kib, iterations, parallelism, Some(KEY_LEN))
+                        .map_err(|_| Error::InvalidKdfParams)?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(password, salt, &mut out[..])
+                    .map_err(|_| Error::InvalidKdfParams)?;
+            }
+            #[cfg(not(feature = "argon2"))]
+            KdfParams::Argon2id { .. } => return Err(Error::UnsupportedAlgorithm),
+        }
+
+        Key::new(&out[..])
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let valid = match *self {
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                (MIN_PBKDF2_ITERATIONS..=MAX_PBKDF2_ITERATIONS).contains(&iterations)
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                (MIN_ARGON2_MEMORY_KIB..=MAX_ARGON2_MEMORY_KIB).contains(&memory_kib)
+                    && (1..=MAX_ARGON2_ITERATIONS).contains(&iterations)
+                    && (1..=MAX_ARGON2_PARALLELISM).contains(&parallelism)
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidKdfParams)
+        }
+    }
+
+    fn encode(&self) -> [u8; 13] {
+        let (id, fields) = match *self {
+            KdfParams::Pbkdf2Sha256 { iterations } => (1, [iterations, 0, 0]),
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => (2, [memory_kib, iterations, parallelism]),
+        };
+
+        let mut out = [0u8; 13];
+        out[0] = id;
+        for (chunk, field) in out[1..].chunks_mut(4).zip(fields) {
+            chunk.copy_from_slice(&field.to_be_bytes());
+        }
+        out
+    }
+
+    /// Decodes the KDF identifier and parameters. Unused parameter fields must
+    /// be zero, so every parameter set has exactly one encoding.
+    fn decode(bytes: &[u8]) -> Result<KdfParams, Error> {
+        let field = |i: usize| {
+            let start = 1 + 4 * i;
+            u32::from_be_bytes([
+                bytes[start],
+                bytes[start + 1],
+                bytes[start + 2],
+                bytes[start + 3],
+            ])
+        };
+        match bytes[0] {
+            1 if field(1) == 0 && field(2) == 0 => Ok(KdfParams::Pbkdf2Sha256 {
+                iterations: field(0),
+            }),
+            1 => Err(Error::InvalidKdfParams),
+            2 => Ok(KdfParams::Argon2id {
+                memory_kib: field(0),
+                iterations: field(1),
+                parallelism: field(2),
+            }),
+            _ => Err(Error::UnsupportedAlgorithm),
+        }
+    }
+}
+
+/// Encrypts the given plaintext under a key derived from the password, using
+/// PBKDF2-HMAC-SHA256 with [`DEFAULT_PBKDF2_ITERATIONS`].
+///
+/// # Arguments
+///
+/// * `password` - The password.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// A blob holding the KDF parameters, the salt and the envelope.
+pub fn encrypt_with_password(password: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    encrypt_with_password_params(password, plaintext, KdfParams::default())
+}
+
+/// Encrypts the given plaintext under a key derived from the password with
+/// the given KDF parameters.
+///
+/// # Arguments
+///
+/// * `password` - The password.
+/// * `plaintext` - The data to encrypt.
+/// * `params` - The key derivation function and its cost parameters.
+///
+/// # Returns
+///
+/// A blob holding the KDF parameters, the salt and the envelope.
+pub fn encrypt_with_password_params(
+    password: &[u8],
+    plaintext: &[u8],
+    params: KdfParams,
+) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    rng::fill(&mut salt)?;
+    let key = params.derive_key(password, &salt)?;
+
+    let sealed = envelope::seal(Algorithm::Aes256Gcm, &key, plaintext)?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + sealed.len());
+    out.push(VERSION);
+    out.extend_from_slice(&params.encode());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Decrypts a blob produced by [`encrypt_with_password`] or
+/// [`encrypt_with_password_params`].
+///
+/// # Arguments
+///
+/// * `password` - The password.
+/// * `blob` - The encrypted blob.
+///
+/// # Returns
+///
+/// The decrypted plaintext. Fails with `Error::InvalidKdfParams` if the stored
+/// parameters are out of bounds, and with `Error::DecryptionFailed` if the password is
+/// wrong or the blob was modified.
+pub fn decrypt_with_password(password: &[u8], blob: &[u8]) -> Result<Vec<u8>, Error> {
+    let (params, salt) = read_header(blob)?;
+    let key = params.derive_key(password, salt)?;
+    envelope::open(&key, &blob[HEADER_LEN..])
+}
+
+/// Reads the KDF parameters stored in a password blob, without deriving a key.
+pub fn kdf_params(blob: &[u8]) -> Result<KdfParams, Error> {
+    read_header(blob).map(|(params, _)| params)
+}
+
+fn read_header(blob: &[u8]) -> Result<(KdfParams, &[u8]), Error> {
+    if blob.len() < HEADER_LEN {
+        return Err(Error::TruncatedInput);
+    }
+    if blob[0] != VERSION {
+        return Err(Error::UnsupportedVersion);
+    }
+    let params = KdfParams::decode(&blob[1..14])?;
+    params.validate()?;
+    Ok((params, &blob[14..HEADER_LEN]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: KdfParams = KdfParams::Pbkdf2Sha256 {
+        iterations: MIN_PBKDF2_ITERATIONS,
+    };
+
+    #[test]
+    fn test_password_round_trip() {
+        let blob = encrypt_with_password(b"correct horse battery staple", b"hello world")
+            .expect("encryption failed");
+
+        assert_eq!(kdf_params(&blob).unwrap(), KdfParams::default());
+        assert_eq!(
+            decrypt_with_password(b"correct horse battery staple", &blob).unwrap(),
+            b"hello world"
+        );
+        assert_eq!(
+            decrypt_with_password(b"Tr0ub4dor&3", &blob),
+            Err(Error::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_stored_params_are_bounded() {
+        let mut blob = encrypt_with_password_params(b"pw", b"hello world", FAST).unwrap();
+
+        // Downgrade the iteration count.
+        blob[2..6].copy_from_slice(&1u32.to_be_bytes());
+        assert_eq!(
+            decrypt_with_password(b"pw", &blob),
+            Err(Error::InvalidKdfParams)
+        );
+
+        // Ask for an unreasonable amount of work.
+        blob[2..6].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            decrypt_with_password(b"pw", &blob),
+            Err(Error::InvalidKdfParams)
+        );
+
+        // PBKDF2 leaves the last two fields unused; they must stay zero.
+        let mut blob = encrypt_with_password_params(b"pw", b"hello world", FAST).unwrap();
+        blob[13] = 1;
+        assert_eq!(
+            decrypt_with_password(b"pw", &blob),
+            Err(Error::InvalidKdfParams)
+        );
+
+        assert_eq!(
+            encrypt_with_password_params(b"pw", b"", KdfParams::Pbkdf2Sha256 { iterations: 1 }),
+            Err(Error::InvalidKdfParams)
+        );
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2id_round_trip() {
+        let params = KdfParams::Argon2id {
+            memory_kib: MIN_ARGON2_MEMORY_KIB,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let blob = encrypt_with_password_params(b"pw", b"hello world", params).unwrap();
+
+        assert_eq!(kdf_params(&blob).unwrap(), params);
+        assert_eq!(decrypt_with_password(b"pw", &blob).unwrap(), b"hello world");
+    }
+}
@@ -0,0 +1,330 @@
+//! An encrypted ring buffer for passing fixed-size records between processes
+//! through shared memory.
+//!
+//! The buffer lives in a region of 64-bit words that both processes map, e.g.
+//! a POSIX shared memory object viewed as `&[AtomicU64]`. Every record is
+//! sealed before it is written to the region, so no plaintext ever reaches
+//! shared memory:
+//!
+//! ```text
+//! word    field
+//! 0..2    ring id, 16 random bytes chosen by `EncryptedRingBuffer::create`
+//! 2       head: sequence number of the next record to write
+//! 3       tail: sequence number of the next record to read
+//! 4..     slots, each holding one AES-256-GCM ciphertext and tag
+//! ```
+//!
+//! Record `n` is sealed in slot `n % capacity` under the nonce `n`, with a key
+//! derived from the master key and the ring id. A fresh ring id for every
+//! [`EncryptedRingBuffer::create`] means re-creating a region under the same
+//! master key never repeats a nonce, and a record replayed into another slot
+//! fails to decrypt.
+//!
+//! The buffer supports exactly one producer calling
+//! [`EncryptedRingBuffer::push`] and one consumer calling
+//! [`EncryptedRingBuffer::pop`], typically in different processes. The
+//! producer should be the process that calls
+//! [`EncryptedRingBuffer::create`]: a producer that attaches instead starts
+//! from the head it finds in the region, and has to trust that it was not
+//! rewound.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::aead::{Algorithm, Cipher, NONCE_LEN, TAG_LEN};
+use crate::error::Error;
+use crate::kdf;
+use crate::key::Key;
+use crate::rng;
+
+const RING_INFO: &[u8] = b"aes_implementation/ring_buffer";
+const HEADER_WORDS: usize = 4;
+const HEAD: usize = 2;
+const TAIL: usize = 3;
+
+/// A view of an encrypted ring buffer in a shared region.
+pub struct EncryptedRingBuffer<'a> {
+    region: &'a [AtomicU64],
+    cipher: Cipher,
+    record_len: usize,
+    capacity: u64,
+    // The producer's own copy of the head. Nonces come from here rather than
+    // from the region, so a process that rewinds the shared head cannot make
+    // the producer reuse one.
+    next: AtomicU64,
+}
+
+impl<'a> EncryptedRingBuffer<'a> {
+    /// Returns the number of words a region needs to hold `capacity` records
+    /// of `record_len` bytes each.
+    pub fn region_words(record_len: usize, capacity: usize) -> usize {
+        HEADER_WORDS + capacity * slot_words(record_len)
+    }
+
+    /// Initializes an empty ring buffer in `region`, discarding its contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The shared region; its length determines the capacity.
+    /// * `master` - A 256-bit key shared by producer and consumer.
+    /// * `record_len` - The length in bytes of every record.
+    ///
+    /// # Returns
+    ///
+    /// The ring buffer, `Error::InvalidSegmentSize` if `record_len` is zero or
+    /// the region holds no slot, or `Error::InvalidKeyLength` if the key is
+    /// not 32 bytes.
+    pub fn create(
+        region: &'a [AtomicU64],
+        master: &Key,
+        record_len: usize,
+    ) -> Result<EncryptedRingBuffer<'a>, Error> {
+        let mut ring_id = [0u8; 16];
+        rng::fill(&mut ring_id)?;
+        let ring = EncryptedRingBuffer::new(region, master, record_len, &ring_id)?;
+
+        for (word, bytes) in region.iter().zip(ring_id.chunks(8)) {
+            word.store(
+                u64::from_le_bytes(bytes.try_into().unwrap()),
+                Ordering::Relaxed,
+            );
+        }
+        ring.next.store(0, Ordering::Relaxed);
+        region[HEAD].store(0, Ordering::Relaxed);
+        region[TAIL].store(0, Ordering::Release);
+        Ok(ring)
+    }
+
+    /// Attaches to a ring buffer that another process created.
+    ///
+    /// # Arguments
+    ///
+    /// * `region` - The shared region passed to [`EncryptedRingBuffer::create`].
+    /// * `master` - The key the buffer was created with.
+    /// * `record_len` - The record length the buffer was created with.
+    ///
+    /// # Returns
+    ///
+    /// The ring buffer, or an error as for [`EncryptedRingBuffer::create`]. A
+    /// wrong key is only detected by [`EncryptedRingBuffer::pop`].
+    pub fn attach(
+        region: &'a [AtomicU64],
+        master: &Key,
+        record_len: usize,
+    ) -> Result<EncryptedRingBuffer<'a>, Error> {
+        let mut ring_id = [0u8; 16];
+        for (bytes, word) in ring_id.chunks_mut(8).zip(region) {
+            bytes.copy_from_slice(&word.load(Ordering::Acquire).to_le_bytes());
+        }
+        EncryptedRingBuffer::new(region, master, record_len, &ring_id)
+    }
+
+    fn new(
+        region: &'a [AtomicU64],
+        master: &Key,
+        record_len: usize,
+        ring_id: &[u8; 16],
+    ) -> Result<EncryptedRingBuffer<'a>, Error> {
+        let capacity = match record_len {
+            0 => 0,
+            _ => region.len().saturating_sub(HEADER_WORDS) / slot_words(record_len),
+        };
+        if capacity == 0 {
+            return Err(Error::InvalidSegmentSize);
+        }
+        Algorithm::Aes256Gcm.check_key_len(master.as_bytes().len())?;
+        let key = kdf::derive_key(master, ring_id, RING_INFO)?;
+        Ok(EncryptedRingBuffer {
+            region,
+            cipher: Cipher::new(Algorithm::Aes256Gcm, &key)?,
+            record_len,
+            capacity: capacity as u64,
+            next: AtomicU64::new(region[HEAD].load(Ordering::Acquire)),
+        })
+    }
+
+    /// Returns the number of records the buffer holds when full.
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Seals a record into the next free slot.
+    ///
+    /// Only one producer may call this at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record; must be exactly the buffer's record length.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` once the record is visible to the consumer, `Ok(false)` if
+    /// the buffer is full, or `Error::InvalidPlaintextLength` if the record
+    /// has the wrong length.
+    pub fn push(&self, record: &[u8]) -> Result<bool, Error> {
+        if record.len() != self.record_len {
+            return Err(Error::InvalidPlaintextLength {
+                expected: self.record_len,
+                actual: record.len(),
+            });
+        }
+        let head = self.next.load(Ordering::Relaxed);
+        let tail = self.region[TAIL].load(Ordering::Acquire);
+        if head.wrapping_sub(tail) >= self.capacity {
+            return Ok(false);
+        }
+
+        let sealed = self.cipher.encrypt_with_nonce(nonce(head), record, &[])?;
+        for (word, bytes) in self.slot(head).iter().zip(sealed.chunks(8)) {
+            let mut padded = [0u8; 8];
+            padded[..bytes.len()].copy_from_slice(bytes);
+            word.store(u64::from_le_bytes(padded), Ordering::Relaxed);
+        }
+        self.next.store(head.wrapping_add(1), Ordering::Relaxed);
+        self.region[HEAD].store(head.wrapping_add(1), Ordering::Release);
+        Ok(true)
+    }
+
+    /// Takes the oldest record out of the buffer and decrypts it.
+    ///
+    /// Only one consumer may call this at a time.
+    ///
+    /// # Returns
+    ///
+    /// The record, `Ok(None)` if the buffer is empty, or
+    /// `Error::DecryptionFailed` if the key is wrong or the region was
+    /// modified. A record that fails to decrypt is still removed, so the
+    /// consumer can carry on with the next one.
+    pub fn pop(&self) -> Result<Option<Vec<u8>>, Error> {
+        let tail = self.region[TAIL].load(Ordering::Relaxed);
+        let head = self.region[HEAD].load(Ordering::Acquire);
+        let len = head.wrapping_sub(tail);
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > self.capacity {
+            return Err(Error::DecryptionFailed);
+        }
+
+        let mut sealed: Vec<u8> = self
+            .slot(tail)
+            .iter()
+            .flat_map(|word| word.load(Ordering::Relaxed).to_le_bytes())
+            .collect();
+        sealed.truncate(self.record_len + TAG_LEN);
+        self.region[TAIL].store(tail.wrapping_add(1), Ordering::Release);
+
+        let record = self.cipher.decrypt_with_aad(&nonce(tail), &sealed, &[])?;
+        Ok(Some(record))
+    }
+
+    /// Returns the number of records waiting to be popped.
+    pub fn len(&self) -> usize {
+        let tail = self.region[TAIL].load(Ordering::Acquire);
+        let head = self.region[HEAD].load(Ordering::Acquire);
+        head.wrapping_sub(tail).min(self.capacity) as usize
+    }
+
+    /// Returns whether no records are waiting to be popped.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slot(&self, sequence: u64) -> &[AtomicU64] {
+        let words = slot_words(self.record_len);
+        let start = HEADER_WORDS + (sequence % self.capacity) as usize * words;
+        &self.region[start..start + words]
+    }
+}
+
+fn slot_words(record_len: usize) -> usize {
+    (record_len + TAG_LEN).div_ceil(8)
+}
+
+fn nonce(sequence: u64) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[4..].copy_from_slice(&sequence.to_be_bytes());
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(words: usize) -> Vec<AtomicU64> {
+        (0..words).map(|_| AtomicU64::new(0)).collect()
+    }
+
+    #[test]
+    fn test_ring_buffer_round_trip() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let shared = region(EncryptedRingBuffer::region_words(10, 3));
+        let producer = EncryptedRingBuffer::create(&shared, &key, 10).unwrap();
+        let consumer = EncryptedRingBuffer::attach(&shared, &key, 10).unwrap();
+        assert_eq!(consumer.capacity(), 3);
+
+        // Wrap around the slots a few times.
+        for round in 0..4u8 {
+            for i in 0..3 {
+                assert!(producer.push(&[round * 3 + i; 10]).unwrap());
+            }
+            assert!(!producer.push(&[0xff; 10]).unwrap());
+            assert_eq!(consumer.len(), 3);
+            for i in 0..3 {
+                assert_eq!(consumer.pop().unwrap().unwrap(), [round * 3 + i; 10]);
+            }
+            assert_eq!(consumer.pop(), Ok(None));
+        }
+
+        // The plaintext never appears in the region.
+        producer.push(b"secret!!!!").unwrap();
+        let bytes: Vec<u8> = shared
+            .iter()
+            .flat_map(|word| word.load(Ordering::Relaxed).to_le_bytes())
+            .collect();
+        assert!(!bytes.windows(10).any(|window| window == b"secret!!!!"));
+
+        assert_eq!(
+            producer.push(b"short"),
+            Err(Error::InvalidPlaintextLength {
+                expected: 10,
+                actual: 5
+            })
+        );
+    }
+
+    #[test]
+    fn test_ring_buffer_detects_tampering() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let shared = region(EncryptedRingBuffer::region_words(8, 2));
+        let producer = EncryptedRingBuffer::create(&shared, &key, 8).unwrap();
+        producer.push(&[1; 8]).unwrap();
+        producer.push(&[2; 8]).unwrap();
+
+        shared[HEADER_WORDS].fetch_xor(1, Ordering::Relaxed);
+        assert_eq!(producer.pop(), Err(Error::DecryptionFailed));
+        assert_eq!(producer.pop().unwrap().unwrap(), [2; 8]);
+
+        let wrong = Key::new(&[7; 32]).unwrap();
+        let consumer = EncryptedRingBuffer::attach(&shared, &wrong, 8).unwrap();
+        producer.push(&[3; 8]).unwrap();
+        assert_eq!(consumer.pop(), Err(Error::DecryptionFailed));
+
+        // Rewinding the shared head does not rewind the producer's nonces.
+        shared[HEAD].store(0, Ordering::Relaxed);
+        producer.push(&[4; 8]).unwrap();
+        assert_eq!(shared[HEAD].load(Ordering::Relaxed), 4);
+
+        // Re-creating the region starts a new ring with a new key.
+        let recreated = EncryptedRingBuffer::create(&shared, &key, 8).unwrap();
+        recreated.push(&[4; 8]).unwrap();
+        assert_eq!(producer.pop(), Err(Error::DecryptionFailed));
+        recreated.push(&[5; 8]).unwrap();
+        assert_eq!(recreated.pop().unwrap().unwrap(), [5; 8]);
+
+        assert_eq!(
+            EncryptedRingBuffer::attach(&shared[..HEADER_WORDS + 2], &key, 8).err(),
+            Some(Error::InvalidSegmentSize)
+        );
+    }
+}
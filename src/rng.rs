@@ -0,0 +1,12 @@
+//! Access to the system random number generator.
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::error::Error;
+
+/// Fills `out` with random bytes from the operating system.
+pub(crate) fn fill(out: &mut [u8]) -> Result<(), Error> {
+    SystemRandom::new()
+        .fill(out)
+        .map_err(|_| Error::RandomFailed)
+}
@@ -0,0 +1,108 @@
+//! Encrypted strings for storage at rest.
+//!
+//! [`protect`] seals a string into an AES-256-GCM envelope and armors it as
+//! text that fits in a database column, a config file or an environment
+//! variable:
+//!
+//! ```text
+//! enc:<envelope, URL-safe base64 without padding>
+//! ```
+//!
+//! [`reveal`] reverses it. The prefix makes protected values easy to tell
+//! apart from plaintext ones, e.g. while migrating a column.
+//!
+//! ```
+//! use aes_implementation::{protect, reveal, Key};
+//!
+//! let key = Key::new(b"an example very very secret key.").unwrap();
+//! let stored = protect(&key, "sk_live_0123456789").unwrap();
+//! assert!(stored.starts_with("enc:"));
+//! assert_eq!(reveal(&key, &stored).unwrap(), "sk_live_0123456789");
+//! ```
+
+use alloc::{format, string::String};
+
+use crate::aead::Algorithm;
+use crate::encoding::{self, Base64Alphabet};
+use crate::envelope;
+use crate::error::Error;
+use crate::key::Key;
+
+/// The prefix in front of every protected string.
+pub const PREFIX: &str = "enc:";
+
+/// Encrypts a string into an armored envelope.
+///
+/// # Arguments
+///
+/// * `key` - A 256-bit key.
+/// * `secret` - The string to protect.
+///
+/// # Returns
+///
+/// The armored envelope, see the module documentation.
+pub fn protect(key: &Key, secret: &str) -> Result<String, Error> {
+    let blob = envelope::seal(Algorithm::Aes256Gcm, key, secret.as_bytes())?;
+    Ok(format!(
+        "{PREFIX}{}",
+        encoding::encode_base64(&blob, Base64Alphabet::UrlSafe)
+    ))
+}
+
+/// Decrypts a string produced by [`protect`].
+///
+/// # Arguments
+///
+/// * `key` - The key the string was protected with.
+/// * `protected` - The armored envelope.
+///
+/// # Returns
+///
+/// The original string. Fails with `Error::InvalidEncoding` if the input is
+/// not an armored envelope, and with `Error::DecryptionFailed` if the key is
+/// wrong or the value was modified.
+pub fn reveal(key: &Key, protected: &str) -> Result<String, Error> {
+    let encoded = protected
+        .strip_prefix(PREFIX)
+        .ok_or(Error::InvalidEncoding)?;
+    let blob = encoding::decode_base64(encoded, Base64Alphabet::UrlSafe)?;
+    let plaintext = envelope::open(key, &blob)?;
+    String::from_utf8(plaintext).map_err(|_| Error::InvalidEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protect_reveal() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let stored = protect(&key, "hello world").unwrap();
+
+        assert!(stored.starts_with(PREFIX));
+        assert!(stored[PREFIX.len()..]
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        assert_eq!(reveal(&key, &stored).unwrap(), "hello world");
+        assert_ne!(protect(&key, "hello world").unwrap(), stored);
+
+        let other = Key::new(b"another example very secret key.").unwrap();
+        assert_eq!(reveal(&other, &stored), Err(Error::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_reveal_rejects_malformed_input() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+
+        assert_eq!(reveal(&key, "hello world"), Err(Error::InvalidEncoding));
+        assert_eq!(reveal(&key, "enc:not base64!"), Err(Error::InvalidEncoding));
+        assert_eq!(reveal(&key, "enc:"), Err(Error::TruncatedInput));
+        assert_eq!(
+            protect(&Key::new(&[0u8; 16]).unwrap(), "hello world"),
+            Err(Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16
+            })
+        );
+    }
+}
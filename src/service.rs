@@ -0,0 +1,234 @@
+//! A shared encryption service for async applications.
+//!
+//! [`EncryptionService::spawn`] moves a [`Keyring`] onto a pool of worker
+//! threads fed by a bounded queue. Request handlers hold a cheap, cloneable
+//! handle and never touch key material themselves. When the queue is full,
+//! [`EncryptionService::encrypt`] and [`EncryptionService::decrypt`] wait for
+//! room, so a burst of requests slows callers down instead of growing memory
+//! without bound.
+//!
+//! The workers exit once every handle has been dropped and the queue has
+//! drained.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::Error;
+use crate::keyring::Keyring;
+
+/// Queue length used when the caller has no preference.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Sizing of an [`EncryptionService`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServiceConfig {
+    /// Jobs that may wait for a worker before submitters are held back.
+    pub queue_capacity: NonZeroUsize,
+    /// Number of worker threads.
+    pub workers: NonZeroUsize,
+}
+
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        ServiceConfig {
+            queue_capacity: NonZeroUsize::new(DEFAULT_QUEUE_CAPACITY).unwrap(),
+            workers: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+}
+
+/// A snapshot of an [`EncryptionService`]'s counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServiceMetrics {
+    /// Jobs waiting in the queue.
+    pub queued: usize,
+    /// Jobs that finished successfully.
+    pub succeeded: u64,
+    /// Jobs that finished with an error.
+    pub failed: u64,
+    /// Total length in bytes of the inputs of finished jobs.
+    pub input_bytes: u64,
+}
+
+enum Operation {
+    Encrypt,
+    Decrypt,
+}
+
+struct Job {
+    operation: Operation,
+    input: Vec<u8>,
+    reply: oneshot::Sender<Result<Vec<u8>, Error>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    input_bytes: AtomicU64,
+}
+
+/// A handle to a running encryption service.
+///
+/// Clones share the same queue and workers.
+#[derive(Clone)]
+pub struct EncryptionService {
+    jobs: mpsc::Sender<Job>,
+    counters: Arc<Counters>,
+}
+
+impl EncryptionService {
+    /// Starts the worker threads and returns a handle to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyring` - The keys to serve; encryption uses its active key.
+    /// * `config` - Queue length and number of workers.
+    pub fn spawn(keyring: Keyring, config: ServiceConfig) -> EncryptionService {
+        let (jobs, queue) = mpsc::channel(config.queue_capacity.get());
+        let queue = Arc::new(Mutex::new(queue));
+        let keyring = Arc::new(keyring);
+        let counters = Arc::new(Counters::default());
+
+        for index in 0..config.workers.get() {
+            let queue = Arc::clone(&queue);
+            let keyring = Arc::clone(&keyring);
+            let counters = Arc::clone(&counters);
+            thread::Builder::new()
+                .name(format!("encryption-service-{index}"))
+                .spawn(move || work(&queue, &keyring, &counters))
+                .expect("failed to spawn encryption service worker");
+        }
+
+        EncryptionService { jobs, counters }
+    }
+
+    /// Encrypts the given plaintext under the keyring's active key.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - The data to encrypt.
+    ///
+    /// # Returns
+    ///
+    /// The encoded envelope, as [`Keyring::encrypt`] returns it, or
+    /// `Error::ServiceUnavailable` if the workers have stopped.
+    pub async fn encrypt(&self, plaintext: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.submit(Operation::Encrypt, plaintext).await
+    }
+
+    /// Decrypts an envelope with the key named in its header.
+    ///
+    /// # Arguments
+    ///
+    /// * `blob` - The encoded envelope.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted plaintext, as [`Keyring::decrypt`] returns it, or
+    /// `Error::ServiceUnavailable` if the workers have stopped.
+    pub async fn decrypt(&self, blob: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.submit(Operation::Decrypt, blob).await
+    }
+
+    /// Returns the current queue length and job counters.
+    pub fn metrics(&self) -> ServiceMetrics {
+        ServiceMetrics {
+            queued: self.jobs.max_capacity() - self.jobs.capacity(),
+            succeeded: self.counters.succeeded.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            input_bytes: self.counters.input_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn submit(&self, operation: Operation, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let (reply, result) = oneshot::channel();
+        let job = Job {
+            operation,
+            input,
+            reply,
+        };
+        self.jobs
+            .send(job)
+            .await
+            .map_err(|_| Error::ServiceUnavailable)?;
+        result.await.map_err(|_| Error::ServiceUnavailable)?
+    }
+}
+
+fn work(queue: &Mutex<mpsc::Receiver<Job>>, keyring: &Keyring, counters: &Counters) {
+    loop {
+        // Only wait for the next job under the lock, so workers run
+        // concurrently. A poisoned lock still guards a usable receiver.
+        let job = queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .blocking_recv();
+        let Some(job) = job else { return };
+
+        let result = match job.operation {
+            Operation::Encrypt => keyring.encrypt(&job.input),
+            Operation::Decrypt => keyring.decrypt(&job.input),
+        };
+        let counter = match result {
+            Ok(_) => &counters.succeeded,
+            Err(_) => &counters.failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        counters
+            .input_bytes
+            .fetch_add(job.input.len() as u64, Ordering::Relaxed);
+
+        // The submitter may have given up waiting; that is not an error here.
+        let _ = job.reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::Algorithm;
+    use crate::key::Key;
+
+    #[tokio::test]
+    async fn test_service_round_trip_and_metrics() {
+        let mut keyring = Keyring::new();
+        keyring
+            .add(b"k1", Algorithm::Aes256Gcm, &Key::new(&[1; 32]).unwrap())
+            .unwrap();
+        let config = ServiceConfig {
+            queue_capacity: NonZeroUsize::new(2).unwrap(),
+            workers: NonZeroUsize::new(3).unwrap(),
+        };
+        let service = EncryptionService::spawn(keyring, config);
+
+        // More concurrent submitters than queue slots and workers.
+        let tasks: Vec<_> = (0..20u8)
+            .map(|i| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    let blob = service.encrypt(vec![i; 10]).await.unwrap();
+                    service.decrypt(blob).await.unwrap()
+                })
+            })
+            .collect();
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap(), vec![i as u8; 10]);
+        }
+
+        assert_eq!(
+            service.decrypt(vec![2; 7]).await,
+            Err(Error::TruncatedInput)
+        );
+
+        let metrics = service.metrics();
+        assert_eq!(metrics.queued, 0);
+        assert_eq!(metrics.succeeded, 40);
+        assert_eq!(metrics.failed, 1);
+        assert!(metrics.input_bytes > 20 * 10);
+    }
+}
@@ -0,0 +1,229 @@
+//! Encryption for object stores.
+//!
+//! [`ObjectStore`] is the minimal interface of a blob store such as S3, GCS
+//! or a local directory. [`EncryptingStore`] wraps any implementation and
+//! encrypts every object before it reaches the backend:
+//!
+//! * Object bodies are sealed with a [`RecordCipher`] keyed by the object
+//!   name, so an object copied under another name fails to decrypt.
+//! * Optionally, object names are encrypted with a [`DeterministicCipher`]
+//!   and stored as URL-safe base64. The same name always maps to the same
+//!   stored name, so lookups still work, but the backend learns nothing from
+//!   the names beyond their equality and approximate length.
+//!
+//! [`MemoryStore`] keeps objects in memory, for tests.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::aead::Algorithm;
+use crate::deterministic::DeterministicCipher;
+use crate::encoding::{self, Base64Alphabet};
+use crate::error::Error;
+use crate::kdf;
+use crate::key::Key;
+use crate::record::RecordCipher;
+
+const NAMES_INFO: &[u8] = b"aes_implementation/store/names";
+const NAME_AAD: &[u8] = b"aes_implementation/store/name";
+
+/// A store of named binary objects.
+pub trait ObjectStore {
+    /// The error type of the backend. Encryption failures are converted into
+    /// it, as [`std::io::Error`] does.
+    type Error: From<Error>;
+
+    /// Returns the object stored under `name`, or `None` if there is none.
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Self::Error>;
+
+    /// Stores `data` under `name`, replacing any previous object.
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Returns the names of all objects, in no particular order.
+    fn list(&self) -> Result<Vec<String>, Self::Error>;
+}
+
+/// An [`ObjectStore`] decorator that encrypts objects, and optionally their
+/// names, before passing them to the inner store.
+pub struct EncryptingStore<S> {
+    inner: S,
+    bodies: RecordCipher,
+    names: Option<DeterministicCipher>,
+}
+
+impl<S: ObjectStore> EncryptingStore<S> {
+    /// Wraps a store, encrypting object bodies but not names.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The backend that holds the ciphertexts.
+    /// * `master` - A 256-bit key.
+    ///
+    /// # Returns
+    ///
+    /// The encrypting store, or `Error::InvalidKeyLength` if the key is not
+    /// 32 bytes.
+    pub fn new(inner: S, master: &Key) -> Result<EncryptingStore<S>, Error> {
+        Ok(EncryptingStore {
+            inner,
+            bodies: RecordCipher::new(Algorithm::Aes256Gcm, master)?,
+            names: None,
+        })
+    }
+
+    /// Wraps a store, encrypting both object bodies and names.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The backend that holds the ciphertexts.
+    /// * `master` - A 256-bit key.
+    ///
+    /// # Returns
+    ///
+    /// The encrypting store, or `Error::InvalidKeyLength` if the key is not
+    /// 32 bytes.
+    pub fn with_encrypted_names(inner: S, master: &Key) -> Result<EncryptingStore<S>, Error> {
+        let names_key = kdf::derive_key(master, b"", NAMES_INFO)?;
+        Ok(EncryptingStore {
+            names: Some(DeterministicCipher::new(&names_key)?),
+            ..EncryptingStore::new(inner, master)?
+        })
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the wrapped store, consuming the decorator.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn stored_name(&self, name: &str) -> Result<String, Error> {
+        match &self.names {
+            Some(names) => Ok(encoding::encode_base64(
+                &names.seal(name.as_bytes(), NAME_AAD)?,
+                Base64Alphabet::UrlSafe,
+            )),
+            None => Ok(name.to_owned()),
+        }
+    }
+
+    fn original_name(&self, stored: String) -> Result<String, Error> {
+        let Some(names) = &self.names else {
+            return Ok(stored);
+        };
+        let sealed = encoding::decode_base64(&stored, Base64Alphabet::UrlSafe)?;
+        String::from_utf8(names.open(&sealed, NAME_AAD)?).map_err(|_| Error::InvalidEncoding)
+    }
+}
+
+impl<S: ObjectStore> ObjectStore for EncryptingStore<S> {
+    type Error = S::Error;
+
+    /// Fetches and decrypts an object. Fails with `Error::DecryptionFailed`
+    /// if the stored object was modified or stored under another name.
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, S::Error> {
+        let Some(blob) = self.inner.get(&self.stored_name(name)?)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.bodies.open(name.as_bytes(), &blob)?))
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), S::Error> {
+        let blob = self.bodies.seal(name.as_bytes(), data)?;
+        self.inner.put(&self.stored_name(name)?, &blob)
+    }
+
+    /// Lists the decrypted object names. Fails if the inner store holds a
+    /// name this store did not encrypt.
+    fn list(&self) -> Result<Vec<String>, S::Error> {
+        self.inner
+            .list()?
+            .into_iter()
+            .map(|stored| Ok(self.original_name(stored)?))
+            .collect()
+    }
+}
+
+/// An [`ObjectStore`] that keeps objects in memory.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn objects(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Vec<u8>>> {
+        // Every operation leaves the map consistent, so a poisoned lock is
+        // still usable.
+        self.objects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    type Error = Error;
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.objects().get(name).cloned())
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> Result<(), Error> {
+        self.objects().insert(name.to_owned(), data.to_vec());
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>, Error> {
+        Ok(self.objects().keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypting_store() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let store = EncryptingStore::new(MemoryStore::new(), &key).unwrap();
+        store.put("reports/2024.csv", b"hello world").unwrap();
+        assert_eq!(
+            store.get("reports/2024.csv").unwrap().unwrap(),
+            b"hello world"
+        );
+        assert_eq!(store.get("missing").unwrap(), None);
+        assert_eq!(store.list().unwrap(), ["reports/2024.csv"]);
+
+        // The backend only sees ciphertext, bound to the object name.
+        let blob = store.inner().get("reports/2024.csv").unwrap().unwrap();
+        assert!(!blob.windows(11).any(|window| window == b"hello world"));
+        store.inner().put("reports/2025.csv", &blob).unwrap();
+        assert_eq!(store.get("reports/2025.csv"), Err(Error::DecryptionFailed));
+    }
+
+    #[test]
+    fn test_encrypted_names() {
+        let key = Key::new(b"an example very very secret key.").unwrap();
+        let store = EncryptingStore::with_encrypted_names(MemoryStore::new(), &key).unwrap();
+        store.put("reports/2024.csv", b"hello world").unwrap();
+        store.put("reports/2024.csv", b"replaced").unwrap();
+        store.put("notes.txt", b"").unwrap();
+
+        let mut names = store.list().unwrap();
+        names.sort();
+        assert_eq!(names, ["notes.txt", "reports/2024.csv"]);
+        assert_eq!(store.get("reports/2024.csv").unwrap().unwrap(), b"replaced");
+
+        let stored = store.inner().list().unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored.iter().all(|name| !name.contains("reports")));
+
+        store.inner().put("plain.txt", b"").unwrap();
+        assert_eq!(store.list(), Err(Error::InvalidEncoding));
+    }
+}
@@ -0,0 +1,465 @@
+//! Chunked streaming encryption for inputs too large to hold in memory.
+//!
+//! The plaintext is split into fixed-size segments, each sealed separately
+//! following the STREAM construction: the nonce of segment `i` is a random
+//! 7-byte prefix, the 32-bit big-endian counter `i` and a final byte that is
+//! `1` for the last segment and `0` otherwise. Reordered, duplicated or
+//! dropped segments fail authentication, and so does a stream cut short on a
+//! segment boundary, because its last surviving segment was not sealed as
+//! final.
+//!
+//! ```text
+//! offset  size  field
+//! 0       4     magic "AEST"
+//! 4       1     version (currently 1)
+//! 5       1     algorithm identifier, see `Algorithm::id`
+//! 6       4     segment size, big-endian
+//! 10      7     nonce prefix
+//! 17      ..    segments: ciphertext || tag, the last one possibly shorter
+//! ```
+//!
+//! The header is authenticated as the associated data of every segment.
+
+use std::io::{self, Read, Write};
+
+use crate::aead::{Algorithm, Cipher, NONCE_LEN, TAG_LEN};
+use crate::error::Error;
+use crate::key::Key;
+use crate::rng;
+
+/// The stream format version written by this crate.
+pub const VERSION: u8 = 1;
+
+/// Length in bytes of the stream header.
+pub const HEADER_LEN: usize = 4 + 1 + 1 + 4 + PREFIX_LEN;
+
+/// Segment size used when the caller has no preference.
+pub const DEFAULT_SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Largest segment size accepted from a stream header, to bound allocations.
+pub const MAX_SEGMENT_SIZE: usize = 16 * 1024 * 1024;
+
+const MAGIC: [u8; 4] = *b"AEST";
+const PREFIX_LEN: usize = NONCE_LEN - 4 - 1;
+
+/// Fields shared by both ends of a stream, and by the async adapters.
+pub(crate) struct Segments {
+    cipher: Cipher,
+    header: [u8; HEADER_LEN],
+    segment_size: usize,
+    counter: u32,
+}
+
+impl Segments {
+    /// Starts a new stream under a fresh random nonce prefix.
+    pub(crate) fn for_encryption(
+        algorithm: Algorithm,
+        key: &Key,
+        segment_size: usize,
+    ) -> Result<Segments, Error> {
+        if segment_size == 0 || segment_size > MAX_SEGMENT_SIZE {
+            return Err(Error::InvalidSegmentSize);
+        }
+        let cipher = Cipher::new(algorithm, key)?;
+
+        let mut prefix = [0u8; PREFIX_LEN];
+        rng::fill(&mut prefix)?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = VERSION;
+        header[5] = algorithm.id();
+        header[6..10].copy_from_slice(&(segment_size as u32).to_be_bytes());
+        header[10..].copy_from_slice(&prefix);
+
+        Ok(Segments {
+            cipher,
+            header,
+            segment_size,
+            counter: 0,
+        })
+    }
+
+    /// Resumes a stream from the header read off the wire.
+    pub(crate) fn from_header(header: [u8; HEADER_LEN], key: &Key) -> Result<Segments, Error> {
+        if header[..4] != MAGIC || header[4] != VERSION {
+            return Err(Error::UnsupportedVersion);
+        }
+        let algorithm = Algorithm::from_id(header[5]).ok_or(Error::UnsupportedAlgorithm)?;
+        let segment_size =
+            u32::from_be_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        if segment_size == 0 || segment_size > MAX_SEGMENT_SIZE {
+            return Err(Error::InvalidSegmentSize);
+        }
+
+        Ok(Segments {
+            cipher: Cipher::new(algorithm, key)?,
+            header,
+            segment_size,
+            counter: 0,
+        })
+    }
+
+    pub(crate) fn header(&self) -> &[u8; HEADER_LEN] {
+        &self.header
+    }
+
+    /// Plaintext bytes per segment; a sealed segment is [`TAG_LEN`] longer.
+    pub(crate) fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    /// Seals the next segment and appends it to `out`.
+    pub(crate) fn seal(
+        &mut self,
+        plaintext: &[u8],
+        last: bool,
+        out: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let nonce = self.nonce(last);
+        let sealed = self
+            .cipher
+            .encrypt_with_nonce(nonce, plaintext, &self.header)?;
+        out.extend_from_slice(&sealed);
+        self.advance()
+    }
+
+    /// Opens the next segment.
+    pub(crate) fn open(&mut self, sealed: &[u8], last: bool) -> Result<Vec<u8>, Error> {
+        if sealed.len() < TAG_LEN {
+            return Err(Error::TruncatedInput);
+        }
+        let nonce = self.nonce(last);
+        let plaintext = self.cipher.decrypt_with_aad(&nonce, sealed, &self.header)?;
+        self.advance()?;
+        Ok(plaintext)
+    }
+
+    fn nonce(&self, last: bool) -> [u8; NONCE_LEN] {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..PREFIX_LEN].copy_from_slice(&self.header[HEADER_LEN - PREFIX_LEN..]);
+        nonce[PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[NONCE_LEN - 1] = last as u8;
+        nonce
+    }
+
+    fn advance(&mut self) -> Result<(), Error> {
+        // Running out of counter values would mean reusing nonces.
+        self.counter = self.counter.checked_add(1).ok_or(Error::NonceExhausted)?;
+        Ok(())
+    }
+}
+
+/// Encrypts everything written to it into the stream format.
+///
+/// Plaintext is buffered until a full segment is available. Call
+/// [`StreamEncryptor::finish`] once all data has been written: it seals the
+/// final segment, and without it the stream is rejected as truncated.
+///
+/// If the underlying writer fails, part of a segment may already have been
+/// written and the stream cannot be continued: every later `write` and
+/// `finish` fails, so a retry cannot produce a corrupt stream.
+pub struct StreamEncryptor<W: Write> {
+    writer: W,
+    segments: Segments,
+    buffer: Vec<u8>,
+    header_written: bool,
+    poisoned: bool,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Creates an encryptor with the default segment size.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the encrypted stream.
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `key` - The key; its length must match the algorithm.
+    pub fn new(writer: W, algorithm: Algorithm, key: &Key) -> Result<Self, Error> {
+        Self::with_segment_size(writer, algorithm, key, DEFAULT_SEGMENT_SIZE)
+    }
+
+    /// Creates an encryptor that seals `segment_size` bytes of plaintext per segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The destination for the encrypted stream.
+    /// * `algorithm` - The AEAD algorithm to use.
+    /// * `key` - The key; its length must match the algorithm.
+    /// * `segment_size` - Plaintext bytes per segment, between 1 and `MAX_SEGMENT_SIZE`.
+    pub fn with_segment_size(
+        writer: W,
+        algorithm: Algorithm,
+        key: &Key,
+        segment_size: usize,
+    ) -> Result<Self, Error> {
+        Ok(StreamEncryptor {
+            writer,
+            segments: Segments::for_encryption(algorithm, key, segment_size)?,
+            buffer: Vec::with_capacity(segment_size),
+            header_written: false,
+            poisoned: false,
+        })
+    }
+
+    /// Seals the buffered plaintext as the final segment and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.seal_segment(true)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn seal_segment(&mut self, last: bool) -> io::Result<()> {
+        self.check_poisoned()?;
+        let result = self.write_segment(last);
+        self.poisoned = result.is_err();
+        result
+    }
+
+    fn check_poisoned(&self) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other(
+                "an earlier write failed; the stream is incomplete",
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_segment(&mut self, last: bool) -> io::Result<()> {
+        if !self.header_written {
+            self.writer.write_all(self.segments.header())?;
+            self.header_written = true;
+        }
+
+        let mut sealed = Vec::with_capacity(self.buffer.len() + TAG_LEN);
+        self.segments.seal(&self.buffer, last, &mut sealed)?;
+        self.writer.write_all(&sealed)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for StreamEncryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_poisoned()?;
+        let mut rest = buf;
+        while !rest.is_empty() {
+            // A full segment is only sealed once more data arrives, since it
+            // may turn out to be the last one.
+            if self.buffer.len() == self.segments.segment_size() {
+                self.seal_segment(false)?;
+            }
+            let take = rest
+                .len()
+                .min(self.segments.segment_size() - self.buffer.len());
+            self.buffer.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decrypts a stream produced by [`StreamEncryptor`].
+///
+/// Segments are authenticated before any of their plaintext is returned. A
+/// read fails with [`io::ErrorKind::InvalidData`] if a segment was modified,
+/// reordered or dropped, or if the stream was truncated.
+pub struct StreamDecryptor<R: Read> {
+    reader: R,
+    segments: Segments,
+    plaintext: Vec<u8>,
+    position: usize,
+    lookahead: Option<u8>,
+    done: bool,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Reads the stream header and creates a decryptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source of the encrypted stream.
+    /// * `key` - The key the stream was encrypted with.
+    pub fn new(mut reader: R, key: &Key) -> io::Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::TruncatedInput.into(),
+            _ => e,
+        })?;
+
+        Ok(StreamDecryptor {
+            reader,
+            segments: Segments::from_header(header, key)?,
+            plaintext: Vec::new(),
+            position: 0,
+            lookahead: None,
+            done: false,
+        })
+    }
+
+    /// Returns the reader, discarding any buffered plaintext.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn open_segment(&mut self) -> io::Result<()> {
+        let sealed_len = self.segments.segment_size() + TAG_LEN;
+        let mut sealed = Vec::with_capacity(sealed_len);
+        sealed.extend(self.lookahead.take());
+        (&mut self.reader)
+            .take((sealed_len - sealed.len()) as u64)
+            .read_to_end(&mut sealed)?;
+
+        // A short segment is the last one; a full one is last only if nothing follows.
+        let last = if sealed.len() < sealed_len {
+            true
+        } else {
+            let mut next = [0u8; 1];
+            let read = loop {
+                match self.reader.read(&mut next) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+            if read == 1 {
+                self.lookahead = Some(next[0]);
+            }
+            read == 0
+        };
+        self.plaintext = self.segments.open(&sealed, last)?;
+        self.position = 0;
+        self.done = last;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamDecryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.open_segment()?;
+        }
+
+        let n = buf.len().min(self.plaintext.len() - self.position);
+        buf[..n].copy_from_slice(&self.plaintext[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> Key {
+        Key::new(b"an example very very secret key.").unwrap()
+    }
+
+    fn encrypt(plaintext: &[u8], segment_size: usize) -> Vec<u8> {
+        let mut encryptor = StreamEncryptor::with_segment_size(
+            Vec::new(),
+            Algorithm::Aes256Gcm,
+            &key(),
+            segment_size,
+        )
+        .unwrap();
+        // Feed the input in uneven pieces to exercise segment buffering.
+        for piece in plaintext.chunks(7) {
+            encryptor.write_all(piece).unwrap();
+        }
+        encryptor.finish().unwrap()
+    }
+
+    fn decrypt(stream: &[u8]) -> io::Result<Vec<u8>> {
+        let mut plaintext = Vec::new();
+        StreamDecryptor::new(stream, &key())?.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_stream_round_trip() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+
+        for segment_size in [1, 16, 100, 1000, 4096] {
+            let stream = encrypt(&plaintext, segment_size);
+            assert_eq!(decrypt(&stream).unwrap(), plaintext);
+        }
+        assert_eq!(decrypt(&encrypt(b"", 16)).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_truncation_and_reordering_are_rejected() {
+        let plaintext = vec![42u8; 64];
+        let stream = encrypt(&plaintext, 16);
+        let sealed_len = 16 + TAG_LEN;
+
+        // Cut on a segment boundary.
+        let truncated = &stream[..HEADER_LEN + 2 * sealed_len];
+        assert_eq!(
+            decrypt(truncated).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // Swap the first two segments.
+        let mut reordered = stream[..HEADER_LEN].to_vec();
+        reordered.extend_from_slice(&stream[HEADER_LEN + sealed_len..HEADER_LEN + 2 * sealed_len]);
+        reordered.extend_from_slice(&stream[HEADER_LEN..HEADER_LEN + sealed_len]);
+        reordered.extend_from_slice(&stream[HEADER_LEN + 2 * sealed_len..]);
+        assert_eq!(
+            decrypt(&reordered).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // Tamper with the header.
+        let mut relabelled = stream.clone();
+        relabelled[HEADER_LEN - 1] ^= 1;
+        assert!(decrypt(&relabelled).is_err());
+    }
+
+    /// Accepts `budget` bytes, then fails every write with `WouldBlock`.
+    struct FlakyWriter {
+        written: Vec<u8>,
+        budget: usize,
+    }
+
+    impl Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let n = buf.len().min(self.budget);
+            self.written.extend_from_slice(&buf[..n]);
+            self.budget -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writer_error_poisons_encryptor() {
+        let writer = FlakyWriter {
+            written: Vec::new(),
+            budget: HEADER_LEN + 10,
+        };
+        let mut encryptor =
+            StreamEncryptor::with_segment_size(writer, Algorithm::Aes256Gcm, &key(), 16).unwrap();
+
+        // The first segment is sealed, then only partly written.
+        encryptor.write_all(&[1; 16]).unwrap();
+        let err = encryptor.write(&[2; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        encryptor.writer.budget = usize::MAX;
+        assert!(encryptor.write(&[2; 16]).is_err());
+        assert!(encryptor.finish().is_err());
+    }
+}
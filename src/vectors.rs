@@ -0,0 +1,228 @@
+//! Canonical cross-language test vectors.
+//!
+//! [`generate`] builds a fixed set of vectors for every algorithm compiled into
+//! this build and for the RFC 8188 content-encoding. The set round-trips
+//! through JSON with [`to_json`] and [`from_json`], so ports of this crate can
+//! check byte-compatibility against the same file, and [`verify`] re-checks a
+//! set against this implementation. All byte strings are lowercase hex.
+
+use serde::{Deserialize, Serialize};
+
+use crate::aead::{Algorithm, Cipher, NONCE_LEN};
+use crate::ece;
+use crate::encoding::{decode_hex, encode_hex};
+use crate::key::Key;
+
+/// A full set of test vectors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VectorSet {
+    /// Vectors for the raw AEAD algorithms.
+    pub aead: Vec<AeadVector>,
+    /// Vectors for the RFC 8188 `aes128gcm` content-encoding.
+    pub ece: Vec<EceVector>,
+}
+
+/// A single AEAD encryption: `ciphertext` is the ciphertext with the tag appended.
+///
+/// `aad` may be omitted from the JSON, in which case it is empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AeadVector {
+    pub name: String,
+    pub algorithm: String,
+    pub key: String,
+    pub nonce: String,
+    #[serde(default)]
+    pub aad: String,
+    pub plaintext: String,
+    pub ciphertext: String,
+}
+
+/// A single `aes128gcm` payload, header included.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EceVector {
+    pub name: String,
+    pub ikm: String,
+    pub salt: String,
+    pub keyid: String,
+    pub rs: u32,
+    pub plaintext: String,
+    pub payload: String,
+}
+
+/// Builds the canonical vector set.
+///
+/// # Returns
+///
+/// The vectors for every algorithm available in this build. AES-192-GCM
+/// vectors are only included with the `aes192` feature.
+pub fn generate() -> VectorSet {
+    let plaintexts: [(&str, Vec<u8>); 4] = [
+        ("empty", Vec::new()),
+        ("short", b"hello world".to_vec()),
+        ("block", vec![0u8; 16]),
+        ("multi-block", (0..100).collect()),
+    ];
+
+    let mut aead = Vec::new();
+    for algorithm in Algorithm::ALL {
+        let key: Vec<u8> = (0..algorithm.key_len() as u8).collect();
+        let Ok(cipher) = Cipher::new(algorithm, &Key::new(&key).expect("valid key length")) else {
+            continue;
+        };
+
+        for (index, (label, plaintext)) in plaintexts.iter().enumerate() {
+            let mut nonce = [0u8; NONCE_LEN];
+            nonce[NONCE_LEN - 1] = index as u8;
+            // Odd-numbered vectors also exercise associated data.
+            let aad: &[u8] = if index % 2 == 1 { b"header" } else { b"" };
+            let ciphertext = cipher
+                .encrypt_with_nonce(nonce, plaintext, aad)
+                .expect("encryption failed");
+            aead.push(AeadVector {
+                name: format!("{}/{}", algorithm.name(), label),
+                algorithm: algorithm.name().to_string(),
+                key: encode_hex(&key),
+                nonce: encode_hex(&nonce),
+                aad: encode_hex(aad),
+                plaintext: encode_hex(plaintext),
+                ciphertext: encode_hex(&ciphertext),
+            });
+        }
+    }
+
+    let ikm: Vec<u8> = (0..16).collect();
+    let salt = [0x5a; ece::SALT_LEN];
+    let mut ece = Vec::new();
+    for (label, keyid, rs, plaintext) in [
+        (
+            "single-record",
+            &b""[..],
+            ece::DEFAULT_RECORD_SIZE,
+            &plaintexts[1].1,
+        ),
+        ("multi-record", &b"k1"[..], 50, &plaintexts[3].1),
+    ] {
+        let payload =
+            ece::encrypt_with_salt(&ikm, &salt, keyid, rs, plaintext).expect("encryption failed");
+        ece.push(EceVector {
+            name: format!("aes128gcm/{}", label),
+            ikm: encode_hex(&ikm),
+            salt: encode_hex(&salt),
+            keyid: encode_hex(keyid),
+            rs,
+            plaintext: encode_hex(plaintext),
+            payload: encode_hex(&payload),
+        });
+    }
+
+    VectorSet { aead, ece }
+}
+
+/// Serializes a vector set as pretty-printed JSON.
+pub fn to_json(set: &VectorSet) -> String {
+    serde_json::to_string_pretty(set).expect("vector sets always serialize")
+}
+
+/// Parses a vector set from JSON.
+pub fn from_json(json: &str) -> Result<VectorSet, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Checks every vector in the set against this implementation.
+///
+/// Each vector is re-encrypted from its inputs and compared byte for byte, then
+/// decrypted back to the plaintext. Vectors for algorithms that are unknown or
+/// not compiled into this build count as failures.
+///
+/// # Arguments
+///
+/// * `set` - The vectors to check.
+///
+/// # Returns
+///
+/// The names of the vectors that did not match; empty if all of them did.
+pub fn verify(set: &VectorSet) -> Vec<&str> {
+    let aead = set
+        .aead
+        .iter()
+        .filter(|vector| verify_aead(vector).is_none())
+        .map(|vector| vector.name.as_str());
+    let ece = set
+        .ece
+        .iter()
+        .filter(|vector| verify_ece(vector).is_none())
+        .map(|vector| vector.name.as_str());
+    aead.chain(ece).collect()
+}
+
+fn verify_aead(vector: &AeadVector) -> Option<()> {
+    let algorithm = Algorithm::ALL
+        .into_iter()
+        .find(|algorithm| algorithm.name() == vector.algorithm)?;
+    let key = Key::for_algorithm(algorithm, &decode_hex(&vector.key).ok()?).ok()?;
+    let cipher = Cipher::new(algorithm, &key).ok()?;
+    let nonce: [u8; NONCE_LEN] = decode_hex(&vector.nonce).ok()?.try_into().ok()?;
+    let aad = decode_hex(&vector.aad).ok()?;
+    let plaintext = decode_hex(&vector.plaintext).ok()?;
+    let ciphertext = decode_hex(&vector.ciphertext).ok()?;
+
+    let sealed = cipher.encrypt_with_nonce(nonce, &plaintext, &aad).ok()?;
+    let opened = cipher.decrypt_with_aad(&nonce, &ciphertext, &aad).ok()?;
+    (sealed == ciphertext && opened == plaintext).then_some(())
+}
+
+fn verify_ece(vector: &EceVector) -> Option<()> {
+    let ikm = decode_hex(&vector.ikm).ok()?;
+    let salt: [u8; ece::SALT_LEN] = decode_hex(&vector.salt).ok()?.try_into().ok()?;
+    let keyid = decode_hex(&vector.keyid).ok()?;
+    let plaintext = decode_hex(&vector.plaintext).ok()?;
+    let payload = decode_hex(&vector.payload).ok()?;
+
+    let sealed = ece::encrypt_with_salt(&ikm, &salt, &keyid, vector.rs, &plaintext).ok()?;
+    let opened = ece::decrypt(&ikm, &payload).ok()?;
+    (sealed == payload && opened == plaintext).then_some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_vectors_verify_after_json_round_trip() {
+        let set = generate();
+        let parsed = from_json(&to_json(&set)).expect("parse failed");
+
+        assert_eq!(parsed, set);
+        assert!(verify(&parsed).is_empty());
+    }
+
+    #[test]
+    fn test_aes_128_gcm_matches_gcm_spec() {
+        // Test case 2 of the GCM specification (McGrew & Viega): zero key,
+        // nonce and plaintext block.
+        let vector = AeadVector {
+            name: "gcm-spec/2".to_string(),
+            algorithm: "AES-128-GCM".to_string(),
+            key: "00".repeat(16),
+            nonce: "00".repeat(12),
+            aad: String::new(),
+            plaintext: "00".repeat(16),
+            ciphertext: "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf"
+                .to_string(),
+        };
+        assert!(verify_aead(&vector).is_some());
+    }
+
+    #[test]
+    fn test_tampered_vector_is_reported() {
+        let mut set = generate();
+        let flipped = if set.aead[1].ciphertext.starts_with("00") {
+            "01"
+        } else {
+            "00"
+        };
+        set.aead[1].ciphertext.replace_range(0..2, flipped);
+
+        assert_eq!(verify(&set), vec![set.aead[1].name.as_str()]);
+    }
+}
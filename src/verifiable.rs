@@ -0,0 +1,162 @@
+//! Envelopes whose authenticity can be checked without the decryption key.
+//!
+//! A verifiable blob is a regular envelope followed by an HMAC-SHA256 tag over
+//! the whole envelope, computed under a separate [`VerificationKey`]:
+//!
+//! ```text
+//! offset  size  field
+//! 0       ..    envelope, see `envelope`
+//! ..      32    HMAC-SHA256 over the envelope
+//! ```
+//!
+//! Auditors hold only the verification key: [`verify`] tells them whether a
+//! blob was produced by a holder of that key and left unmodified, but they
+//! cannot decrypt it. Because the MAC covers the ciphertext rather than the
+//! plaintext, it can be checked without decrypting. Holders of the
+//! encryption key open blobs with [`open`], which checks both layers.
+//!
+//! Anyone with the verification key can also produce valid tags, so it
+//! proves integrity towards the auditors, not which party wrote a blob.
+
+use alloc::vec::Vec;
+
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use zeroize::Zeroize;
+
+use crate::aead::Algorithm;
+use crate::envelope;
+use crate::error::Error;
+use crate::key::Key;
+
+/// Length in bytes of a verification key and of the tag it produces.
+pub const VERIFICATION_KEY_LEN: usize = 32;
+
+/// The HMAC-SHA256 key that verifies blobs but cannot decrypt them.
+#[derive(Clone)]
+pub struct VerificationKey([u8; VERIFICATION_KEY_LEN]);
+
+impl VerificationKey {
+    /// Generates a random verification key.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::RandomFailed` if the generator failed.
+    pub fn generate(rng: &SystemRandom) -> Result<VerificationKey, Error> {
+        let mut key = VerificationKey([0; VERIFICATION_KEY_LEN]);
+        rng.fill(&mut key.0).map_err(|_| Error::RandomFailed)?;
+        Ok(key)
+    }
+
+    /// Creates a verification key from raw bytes.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `Error::InvalidKeyLength` unless `bytes` is
+    /// [`VERIFICATION_KEY_LEN`] bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<VerificationKey, Error> {
+        let bytes = bytes.try_into().map_err(|_| Error::InvalidKeyLength {
+            expected: VERIFICATION_KEY_LEN,
+            actual: bytes.len(),
+        })?;
+        Ok(VerificationKey(bytes))
+    }
+
+    /// Returns the raw key material.
+    pub fn as_bytes(&self) -> &[u8; VERIFICATION_KEY_LEN] {
+        &self.0
+    }
+
+    fn hmac_key(&self) -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, &self.0)
+    }
+}
+
+impl Drop for VerificationKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Encrypts the given plaintext into an AES-256-GCM envelope and appends a
+/// tag under the verification key.
+///
+/// # Arguments
+///
+/// * `key` - A 256-bit encryption key.
+/// * `verification_key` - The key auditors verify with.
+/// * `plaintext` - The data to encrypt.
+///
+/// # Returns
+///
+/// The blob laid out as in the module documentation.
+pub fn seal(
+    key: &Key,
+    verification_key: &VerificationKey,
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let mut blob = envelope::seal(Algorithm::Aes256Gcm, key, plaintext)?;
+    let tag = hmac::sign(&verification_key.hmac_key(), &blob);
+    blob.extend_from_slice(tag.as_ref());
+    Ok(blob)
+}
+
+/// Checks a blob's tag without decrypting it.
+///
+/// # Returns
+///
+/// `Ok(())` if the blob is unmodified, `Error::TruncatedInput` if it is too
+/// short to hold a tag, or `Error::InvalidSignature` otherwise.
+pub fn verify(verification_key: &VerificationKey, blob: &[u8]) -> Result<(), Error> {
+    split(verification_key, blob).map(|_| ())
+}
+
+/// Checks a blob's tag, then decrypts the envelope.
+///
+/// # Returns
+///
+/// The plaintext. Fails like [`verify`] if the tag does not match, and like
+/// [`envelope::open`] if the encryption key is wrong.
+pub fn open(key: &Key, verification_key: &VerificationKey, blob: &[u8]) -> Result<Vec<u8>, Error> {
+    envelope::open(key, split(verification_key, blob)?)
+}
+
+/// Verifies the tag and returns the envelope in front of it.
+fn split<'a>(verification_key: &VerificationKey, blob: &'a [u8]) -> Result<&'a [u8], Error> {
+    let envelope_len = blob
+        .len()
+        .checked_sub(VERIFICATION_KEY_LEN)
+        .ok_or(Error::TruncatedInput)?;
+    let (envelope, tag) = blob.split_at(envelope_len);
+    hmac::verify(&verification_key.hmac_key(), envelope, tag)
+        .map_err(|_| Error::InvalidSignature)?;
+    Ok(envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auditor_verifies_without_decrypting() {
+        let rng = SystemRandom::new();
+        let key = Key::generate(&rng).unwrap();
+        let auditor = VerificationKey::generate(&rng).unwrap();
+
+        let blob = seal(&key, &auditor, b"hello world").unwrap();
+        verify(&auditor, &blob).unwrap();
+        assert_eq!(open(&key, &auditor, &blob).unwrap(), b"hello world");
+
+        let mut tampered = blob.clone();
+        tampered[envelope::MIN_HEADER_LEN] ^= 1;
+        assert_eq!(verify(&auditor, &tampered), Err(Error::InvalidSignature));
+
+        let other = VerificationKey::from_bytes(&[7; VERIFICATION_KEY_LEN]).unwrap();
+        assert_eq!(verify(&other, &blob), Err(Error::InvalidSignature));
+        assert_eq!(verify(&auditor, &blob[..31]), Err(Error::TruncatedInput));
+
+        // The verification key alone does not decrypt.
+        let wrong = Key::generate(&rng).unwrap();
+        assert_eq!(open(&wrong, &auditor, &blob), Err(Error::DecryptionFailed));
+    }
+}
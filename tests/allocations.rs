@@ -0,0 +1,69 @@
+// Checks the allocation behaviour of the envelope fast paths. The counting
+// allocator replaces the global allocator for the whole binary, so it lives
+// in its own integration test rather than in the library's unit tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use aes_implementation::aead::{Algorithm, Cipher};
+use aes_implementation::envelope::{self, MIN_HEADER_LEN};
+use aes_implementation::key::Key;
+
+// Counts the heap allocations made by each thread, so that tests running in
+// parallel do not see each other's allocations.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_in_place_round_trip_does_not_allocate() {
+    let key = Key::new(b"an example very very secret key.").unwrap();
+    let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+    let packet: Vec<u8> = (0..64 * 1024).map(|i| i as u8).collect();
+
+    let mut buf = Vec::with_capacity(packet.len() + MIN_HEADER_LEN);
+    buf.extend_from_slice(&packet);
+    let (sealed, allocations) = count_allocations(|| envelope::seal_in_place(&cipher, &mut buf));
+    sealed.unwrap();
+    assert_eq!(allocations, 0);
+
+    let (opened, allocations) = count_allocations(|| {
+        envelope::open_in_place(&key, &mut buf).map(|plaintext| plaintext == &packet[..])
+    });
+    assert_eq!(opened, Ok(true));
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_seal_vectored_copies_once() {
+    let key = Key::new(b"an example very very secret key.").unwrap();
+    let cipher = Cipher::new(Algorithm::Aes256Gcm, &key).unwrap();
+    let body = vec![7u8; 64 * 1024];
+    let parts: [&[u8]; 4] = [b"header:", &body, b"", b":trailer"];
+
+    let (blob, allocations) = count_allocations(|| envelope::seal_vectored(&cipher, &parts));
+    blob.unwrap();
+    assert_eq!(allocations, 1);
+}